@@ -18,6 +18,8 @@ mod es_parser;
 
 pub(crate) mod consts;
 pub(crate) mod event_stream;
+#[cfg(feature = "json")]
+pub(crate) mod payload;
 
 /// Single-line parser comparison
 fn bench_parse_line(c: &mut Criterion) {
@@ -102,5 +104,13 @@ fn bench_event_stream(c: &mut Criterion) {
     group.finish();
 }
 
+#[cfg(feature = "json")]
+criterion_group!(
+    benches,
+    bench_parse_line,
+    bench_event_stream,
+    payload::bench_payload
+);
+#[cfg(not(feature = "json"))]
 criterion_group!(benches, bench_parse_line, bench_event_stream);
 criterion_main!(benches);
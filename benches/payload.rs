@@ -0,0 +1,81 @@
+#![cfg(feature = "json")]
+
+use std::hint::black_box;
+
+use bytes::Bytes;
+use criterion::{BenchmarkId, Criterion};
+use futures::stream::{self, StreamExt};
+use sseer::{
+    event::Event,
+    json_stream::{PayloadStream, format::Format},
+};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type")]
+#[allow(dead_code)]
+enum Response {
+    #[serde(rename = "response.output_text.delta")]
+    Delta {
+        item_id: String,
+        delta: String,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_text.done")]
+    Done { item_id: String, text: String },
+}
+
+/// Parse the events up front so only deserialising the data is measured
+fn load_events(bytes: &[u8]) -> Vec<Event> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let s = stream::iter([Ok::<_, ()>(Bytes::copy_from_slice(bytes))]);
+        sseer::EventStream::new(s)
+            .map(|event| event.unwrap())
+            .collect()
+            .await
+    })
+}
+
+fn run_format<F>(events: &[Event])
+where
+    F: Format,
+{
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let s = stream::iter(events.iter().cloned().map(Ok::<_, ()>));
+        // the stream ends each response with `[DONE]`
+        let mut payloads = PayloadStream::<Response, _, F>::new(s).skip_non_json();
+        while let Some(item) = payloads.next().await {
+            let _ = black_box(item.unwrap());
+        }
+    });
+}
+
+/// Deserialising the same events with each JSON [`Format`]
+pub fn bench_payload(c: &mut Criterion) {
+    let events = load_events(include_bytes!("../bench_data/ai_stream.bin"));
+
+    let mut group = c.benchmark_group("payload");
+    group.bench_with_input(
+        BenchmarkId::new("json", "ai_stream"),
+        &events,
+        |b, events| {
+            b.iter(|| run_format::<sseer::json_stream::format::Json>(events));
+        },
+    );
+
+    #[cfg(feature = "simd-json")]
+    group.bench_with_input(
+        BenchmarkId::new("simd_json", "ai_stream"),
+        &events,
+        |b, events| {
+            b.iter(|| run_format::<sseer::json_stream::format::SimdJson>(events));
+        },
+    );
+
+    group.finish();
+}
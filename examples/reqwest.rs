@@ -36,6 +36,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                         println!("---");
                     }
+                    StreamEvent::Comment(comment) => {
+                        println!("Comment: {}", comment);
+                    }
                 }
             }
             Err(e) => {
@@ -0,0 +1,207 @@
+//! [`tokio_util::codec`] support, read SSE from any [`AsyncRead`][tokio::io::AsyncRead] and write it to any
+//! [`AsyncWrite`][tokio::io::AsyncWrite] through [`Framed`][tokio_util::codec::Framed]
+
+use bytes::BytesMut;
+use bytes_utils::Str;
+use tokio_util::codec;
+
+use crate::{
+    encoder::{EventEncoder, EventFrame},
+    errors::CodecError,
+    event::Event,
+    event_stream::{
+        CommentMode, EmitComments, Limits, SkipComments, Utf8Decoding, decoder::Decoder,
+    },
+};
+
+/// [`Decoder`][codec::Decoder] of [`Event`]s and [`Encoder`][codec::Encoder] of [`Event`]s and [`EventFrame`]s, built
+/// on [`Decoder`] and [`EventEncoder`]
+///
+/// ```rust
+/// use futures::{SinkExt, StreamExt};
+/// use sseer::{codec::EventCodec, encoder::EventFrame};
+/// use tokio_util::codec::{FramedRead, FramedWrite};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (client, server) = tokio::io::duplex(1024);
+///
+/// let mut sink = FramedWrite::new(server, EventCodec::new());
+/// sink.send(EventFrame::new().data("hello")).await.unwrap();
+///
+/// let mut events = FramedRead::new(client, EventCodec::new());
+/// assert_eq!(events.next().await.unwrap().unwrap().data, "hello");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EventCodec<M = SkipComments> {
+    decoder: Decoder<M>,
+    encoder: EventEncoder,
+}
+
+impl Default for EventCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventCodec {
+    /// Create a new [`EventCodec`]
+    pub fn new() -> Self {
+        Self {
+            decoder: Decoder::new(),
+            encoder: EventEncoder::new(),
+        }
+    }
+
+    /// Decode comment lines as well as events
+    pub fn with_comments(self) -> EventCodec<EmitComments> {
+        EventCodec {
+            decoder: self.decoder.with_comments(),
+            encoder: self.encoder,
+        }
+    }
+}
+
+impl<M> EventCodec<M> {
+    /// Cap how much the decoding side buffers, see [`Limits`]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.decoder = self.decoder.with_limits(limits);
+        self
+    }
+
+    /// Choose how incoming bytes are decoded into text, see [`Utf8Decoding`]. Defaults to [`Utf8Decoding::Strict`].
+    pub fn with_utf8_decoding(mut self, utf8: Utf8Decoding) -> Self {
+        self.decoder = self.decoder.with_utf8_decoding(utf8);
+        self
+    }
+
+    /// Reference to the last event id decoded
+    pub fn last_event_id(&self) -> &Str {
+        self.decoder.last_event_id()
+    }
+
+    /// Mutable reference to the [`EventEncoder`] used for writing, to check or set the last event id it sent
+    pub fn encoder_mut(&mut self) -> &mut EventEncoder {
+        &mut self.encoder
+    }
+}
+
+impl<M> codec::Decoder for EventCodec<M>
+where
+    M: CommentMode,
+{
+    type Item = M::Item;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !src.is_empty() {
+            // hand over everything read so far, splitting it off doesn't copy
+            self.decoder.feed(src.split().freeze());
+        }
+        Ok(self.decoder.decode()?)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !src.is_empty() {
+            self.decoder.feed(src.split().freeze());
+        }
+        self.decoder.finish();
+        Ok(self.decoder.decode()?)
+    }
+}
+
+impl<M> codec::Encoder<Event> for EventCodec<M> {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: Event, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        Ok(self.encoder.encode(&item, dst)?)
+    }
+}
+
+impl<M> codec::Encoder<EventFrame> for EventCodec<M> {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: EventFrame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        Ok(self.encoder.encode_frame(&item, dst)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{errors::DecodeError, event::EventOrComment};
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    #[tokio::test]
+    async fn codec_round_trip() {
+        let (client, server) = tokio::io::duplex(16);
+        let events = vec![
+            Event {
+                event: Str::from_static("update"),
+                data: Str::from_static("one\ntwo"),
+                id: Str::from_static("1"),
+                retry: None,
+            },
+            Event {
+                event: Str::from_static("message"),
+                data: Str::from_static("three"),
+                id: Str::from_static("1"),
+                retry: None,
+            },
+        ];
+
+        let writer = {
+            let events = events.clone();
+            tokio::spawn(async move {
+                let mut sink = FramedWrite::new(server, EventCodec::new());
+                sink.send(EventFrame::new().comment("hi")).await.unwrap();
+                for event in events {
+                    sink.send(event).await.unwrap();
+                }
+            })
+        };
+
+        let received = FramedRead::new(client, EventCodec::new().with_comments())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        writer.await.unwrap();
+
+        let mut expected = vec![EventOrComment::Comment(Str::from_static("hi"))];
+        expected.extend(events.into_iter().map(EventOrComment::Event));
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn codec_eof() {
+        let (client, mut server) = tokio::io::duplex(64);
+        server
+            .write_all(b"data: done\r\rdata: unfinished\n")
+            .await
+            .unwrap();
+        drop(server);
+
+        let received = FramedRead::new(client, EventCodec::new())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].as_ref().unwrap().data, "done");
+
+        let (client, mut server) = tokio::io::duplex(64);
+        server.write_all(b"data: \xFF\n\n").await.unwrap();
+        drop(server);
+
+        let received = FramedRead::new(client, EventCodec::new())
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            received[..],
+            [Err(CodecError::Decode(DecodeError::Utf8Error(_)))]
+        ));
+    }
+}
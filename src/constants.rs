@@ -7,6 +7,9 @@ pub(crate) const LF: u8 = b'\n';
 /// Carriage return byte
 pub(crate) const CR: u8 = b'\r';
 
+/// U+FFFD, what invalid UTF-8 gets replaced with when decoding lossily
+pub(crate) const REPLACEMENT_CHARACTER: &str = "\u{FFFD}";
+
 /// Byte Order Mark as char
 const BOM_CHAR: char = '\u{FEFF}';
 const BOM_LEN: usize = BOM_CHAR.len_utf8();
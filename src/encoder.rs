@@ -0,0 +1,357 @@
+//! Serialisation of [`Event`]s into the `text/event-stream` wire format, the inverse of [`EventStream`][crate::EventStream]
+
+use core::time::Duration;
+
+use bytes::{BufMut, BytesMut};
+use bytes_utils::Str;
+
+use crate::{
+    constants::{CR, EMPTY_STR, LF, MESSAGE_STR},
+    errors::EncodeError,
+    event::Event,
+};
+
+/// A partial event where only the fields that have been set get written, useful for sending lone `retry:` or `id:` updates and comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFrame {
+    pub event: Option<Str>,
+    pub data: Option<Str>,
+    pub id: Option<Str>,
+    pub retry: Option<Duration>,
+    pub comment: Option<Str>,
+}
+
+impl EventFrame {
+    /// Create an empty [`EventFrame`], encoding this as-is only writes the blank line that ends an event
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the event type
+    pub fn event(mut self, event: impl Into<Str>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set the data, any line breaks are split across multiple `data:` lines
+    pub fn data(mut self, data: impl Into<Str>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Set the id, an empty id resets the last event id of the receiver
+    pub fn id(mut self, id: impl Into<Str>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set the reconnection time, written in whole milliseconds
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Set a comment to write before the fields, any line breaks are split across multiple comment lines
+    pub fn comment(mut self, comment: impl Into<Str>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+}
+
+/// Writes [`Event`]s and [`EventFrame`]s into a [`BytesMut`].
+///
+/// The encoder remembers the last id it wrote so that an `id:` line is only sent when the id changes, which is what makes
+/// feeding the output back through [`EventStream`][crate::EventStream] give back the same [`Event`]s. Line breaks in `data` are
+/// always written as LF so CR and CRLF come back as LF, and [`Event::retry`] loses any precision below a millisecond.
+#[derive(Debug, Clone)]
+pub struct EventEncoder {
+    last_event_id: Str,
+}
+
+impl Default for EventEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventEncoder {
+    /// Create a new [`EventEncoder`]
+    pub const fn new() -> Self {
+        Self {
+            last_event_id: EMPTY_STR,
+        }
+    }
+
+    /// Set the last event id the receiver is known to have, useful when resuming a stream
+    pub fn set_last_event_id(&mut self, id: impl Into<Str>) {
+        self.last_event_id = id.into()
+    }
+
+    /// Reference to the last event id written by this encoder
+    pub fn last_event_id(&self) -> &Str {
+        &self.last_event_id
+    }
+
+    /// Write a full [`Event`] into `buf`. Nothing is written if the event is rejected.
+    pub fn encode(&mut self, event: &Event, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        validate_event_type(&event.event)?;
+        validate_id(&event.id)?;
+
+        buf.reserve(event.event.len() + event.data.len() + event.id.len() + 32);
+
+        if !event.event.is_empty() && event.event != MESSAGE_STR {
+            put_field(buf, b"event", &event.event);
+        }
+        if event.id != self.last_event_id {
+            put_field(buf, b"id", &event.id);
+            self.last_event_id = event.id.clone();
+        }
+        if let Some(retry) = event.retry {
+            put_retry(buf, retry);
+        }
+        // always write data, an event without a data line never gets dispatched
+        put_multiline(buf, b"data", &event.data);
+        buf.put_u8(LF);
+
+        Ok(())
+    }
+
+    /// Write an [`EventFrame`] into `buf`. Nothing is written if the frame is rejected.
+    pub fn encode_frame(
+        &mut self,
+        frame: &EventFrame,
+        buf: &mut BytesMut,
+    ) -> Result<(), EncodeError> {
+        if let Some(event) = &frame.event {
+            validate_event_type(event)?;
+        }
+        if let Some(id) = &frame.id {
+            validate_id(id)?;
+        }
+
+        if let Some(comment) = &frame.comment {
+            put_comment(buf, comment);
+        }
+        if let Some(event) = &frame.event {
+            put_field(buf, b"event", event);
+        }
+        if let Some(id) = &frame.id {
+            put_field(buf, b"id", id);
+            self.last_event_id = id.clone();
+        }
+        if let Some(retry) = frame.retry {
+            put_retry(buf, retry);
+        }
+        if let Some(data) = &frame.data {
+            put_multiline(buf, b"data", data);
+        }
+        buf.put_u8(LF);
+
+        Ok(())
+    }
+
+    /// Write a comment into `buf`, any line breaks are split across multiple comment lines. Comments don't end an event so they can be sent at any time.
+    pub fn encode_comment(&self, comment: &str, buf: &mut BytesMut) {
+        put_comment(buf, comment);
+    }
+}
+
+fn validate_event_type(event: &str) -> Result<(), EncodeError> {
+    match memchr::memchr2(CR, LF, event.as_bytes()) {
+        Some(_) => Err(EncodeError::EventContainsNewline),
+        None => Ok(()),
+    }
+}
+
+fn validate_id(id: &str) -> Result<(), EncodeError> {
+    if memchr::memchr2(CR, LF, id.as_bytes()).is_some() {
+        return Err(EncodeError::IdContainsNewline);
+    }
+    if memchr::memchr(0, id.as_bytes()).is_some() {
+        return Err(EncodeError::IdContainsNull);
+    }
+    Ok(())
+}
+
+/// Writes `name: value\n`, or just `name\n` if the value is empty. The parser strips a single leading space so values starting with a space survive.
+fn put_field(buf: &mut BytesMut, name: &[u8], value: &str) {
+    buf.extend_from_slice(name);
+    if !value.is_empty() {
+        buf.extend_from_slice(b": ");
+        buf.extend_from_slice(value.as_bytes());
+    }
+    buf.put_u8(LF);
+}
+
+/// Writes one `name: line` per line in `value`
+fn put_multiline(buf: &mut BytesMut, name: &[u8], value: &str) {
+    for_each_line(value, |line| put_field(buf, name, line));
+}
+
+/// Writes one `: line` per line in `comment`, an empty comment still needs the colon or it would be read as the end of an event
+fn put_comment(buf: &mut BytesMut, comment: &str) {
+    for_each_line(comment, |line| {
+        buf.put_u8(b':');
+        if !line.is_empty() {
+            buf.put_u8(b' ');
+            buf.extend_from_slice(line.as_bytes());
+        }
+        buf.put_u8(LF);
+    });
+}
+
+/// Calls `f` on each line of `value`, splitting on CR, LF and CRLF the same way the parser does
+fn for_each_line(value: &str, mut f: impl FnMut(&str)) {
+    let bytes = value.as_bytes();
+    let mut start = 0;
+    while let Some(pos) = memchr::memchr2(CR, LF, &bytes[start..]) {
+        let end = start + pos;
+        f(&value[start..end]);
+        start = if bytes[end] == CR && bytes.get(end + 1) == Some(&LF) {
+            end + 2
+        } else {
+            end + 1
+        };
+    }
+    f(&value[start..]);
+}
+
+fn put_retry(buf: &mut BytesMut, retry: Duration) {
+    let mut millis = u64::try_from(retry.as_millis()).unwrap_or(u64::MAX);
+
+    // u64::MAX is 20 digits long
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (millis % 10) as u8;
+        millis /= 10;
+        if millis == 0 {
+            break;
+        }
+    }
+
+    buf.extend_from_slice(b"retry: ");
+    buf.extend_from_slice(&digits[start..]);
+    buf.put_u8(LF);
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::*;
+    use crate::EventStream;
+    use futures::prelude::*;
+
+    async fn round_trip(events: &[Event]) -> Vec<Event> {
+        let mut encoder = EventEncoder::new();
+        let mut buf = BytesMut::new();
+        for event in events {
+            encoder.encode(event, &mut buf).unwrap();
+        }
+
+        EventStream::new(futures::stream::iter(vec![Ok::<_, ()>(buf.freeze())]))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+    }
+
+    fn event(event: &'static str, data: &'static str, id: &'static str) -> Event {
+        Event {
+            event: Str::from_static(event),
+            data: Str::from_static(data),
+            id: Str::from_static(id),
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn encode_wire_format() {
+        let mut encoder = EventEncoder::new();
+        let mut buf = BytesMut::new();
+
+        encoder
+            .encode(&event("message", "hello", ""), &mut buf)
+            .unwrap();
+        encoder
+            .encode(
+                &Event {
+                    retry: Some(Duration::from_millis(1500)),
+                    ..event("update", "a\nb\r\nc", "1")
+                },
+                &mut buf,
+            )
+            .unwrap();
+        encoder
+            .encode(&event("message", "", "1"), &mut buf)
+            .unwrap();
+        encoder.encode_comment("ping", &mut buf);
+        encoder.encode_comment("", &mut buf);
+
+        assert_eq!(
+            &buf[..],
+            b"data: hello\n\nevent: update\nid: 1\nretry: 1500\ndata: a\ndata: b\ndata: c\n\ndata\n\n: ping\n:\n"
+        );
+    }
+
+    #[test]
+    fn encode_frame() {
+        let mut encoder = EventEncoder::new();
+        let mut buf = BytesMut::new();
+
+        encoder
+            .encode_frame(
+                &EventFrame::new()
+                    .comment("hi")
+                    .id("5")
+                    .retry(Duration::from_secs(3)),
+                &mut buf,
+            )
+            .unwrap();
+
+        assert_eq!(&buf[..], b": hi\nid: 5\nretry: 3000\n\n");
+        assert_eq!(encoder.last_event_id(), &Str::from_static("5"));
+    }
+
+    #[test]
+    fn encode_rejects_line_breaks() {
+        let mut encoder = EventEncoder::new();
+        let mut buf = BytesMut::new();
+
+        assert_eq!(
+            encoder.encode(&event("a\nb", "data", ""), &mut buf),
+            Err(EncodeError::EventContainsNewline)
+        );
+        assert_eq!(
+            encoder.encode(&event("message", "data", "a\rb"), &mut buf),
+            Err(EncodeError::IdContainsNewline)
+        );
+        assert_eq!(
+            encoder.encode(&event("message", "data", "a\0b"), &mut buf),
+            Err(EncodeError::IdContainsNull)
+        );
+        assert_eq!(
+            encoder.encode_frame(&EventFrame::new().event("a\r\nb"), &mut buf),
+            Err(EncodeError::EventContainsNewline)
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn encode_round_trip() {
+        let events = vec![
+            event("message", "hello", ""),
+            event("add", "73857293", "1"),
+            event("message", "two\nlines", "1"),
+            event("message", "", "2"),
+            event("message", "\n", ""),
+            event("message", " leading space", ""),
+            Event {
+                retry: Some(Duration::from_millis(2500)),
+                ..event("remove", "trailing newline\n", "3")
+            },
+        ];
+
+        assert_eq!(round_trip(&events).await, events);
+    }
+}
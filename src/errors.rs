@@ -5,19 +5,28 @@ use core::{
     str::Utf8Error,
 };
 
+#[cfg(any(feature = "http-body", feature = "event-source"))]
+pub mod http;
+#[cfg(any(feature = "http-body", feature = "event-source"))]
+pub use http::InvalidResponse;
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
+#[cfg(feature = "tokio-codec")]
+pub use codec::CodecError;
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
 #[cfg(feature = "reqwest")]
 pub use reqwest::CantCloneError;
 
 macro_rules! impl_samey_error {
-    ($vis:vis enum $name:ident) => {
+    ($vis:vis enum $name:ident $({ $($(#[$meta:meta])* $variant:ident($ty:ty)),* $(,)? })?) => {
         #[derive(Debug, PartialEq)]
         $vis enum $name<E> {
             /// Something went wrong with the underlying stream
             Transport(E),
             /// The stream had invalid utf8
             Utf8Error(Utf8Error),
+            $($($(#[$meta])* $variant($ty),)*)?
         }
 
         impl<E> From<Utf8Error> for $name<E> {
@@ -34,6 +43,7 @@ macro_rules! impl_samey_error {
                 match self {
                     $name::Transport(e) => e.fmt(f),
                     $name::Utf8Error(e) => e.fmt(f),
+                    $($($name::$variant(e) => e.fmt(f),)*)?
                 }
             }
         }
@@ -42,5 +52,90 @@ macro_rules! impl_samey_error {
     };
 }
 
-impl_samey_error!(pub enum EventStreamError);
+impl_samey_error!(
+    pub enum EventStreamError {
+        /// One of the stream's [`Limits`][crate::event_stream::Limits] was exceeded and it was set to error out
+        LimitExceeded(LimitExceeded),
+    }
+);
 impl_samey_error!(pub enum Utf8StreamError);
+
+/// Error from a [`Decoder`][crate::event_stream::decoder::Decoder], everything an event stream can go wrong with apart
+/// from the transport
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// A line had invalid utf8
+    Utf8Error(Utf8Error),
+    /// One of the decoder's [`Limits`][crate::event_stream::Limits] was exceeded and it was set to error out
+    LimitExceeded(LimitExceeded),
+}
+
+impl From<Utf8Error> for DecodeError {
+    fn from(value: Utf8Error) -> Self {
+        Self::Utf8Error(value)
+    }
+}
+
+impl<E> From<DecodeError> for EventStreamError<E> {
+    fn from(value: DecodeError) -> Self {
+        match value {
+            DecodeError::Utf8Error(e) => Self::Utf8Error(e),
+            DecodeError::LimitExceeded(e) => Self::LimitExceeded(e),
+        }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::Utf8Error(e) => e.fmt(f),
+            DecodeError::LimitExceeded(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Which of the [`Limits`][crate::event_stream::Limits] an event stream ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// A single line was longer than [`Limits::max_line_len`][crate::event_stream::Limits::max_line_len]
+    LineLength,
+    /// An event's data was larger than [`Limits::max_event_data`][crate::event_stream::Limits::max_event_data]
+    EventData,
+    /// More bytes were held for the event in progress than [`Limits::max_buffered`][crate::event_stream::Limits::max_buffered]
+    Buffered,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LimitExceeded::LineLength => "line length limit exceeded".fmt(f),
+            LimitExceeded::EventData => "event data size limit exceeded".fmt(f),
+            LimitExceeded::Buffered => "buffered bytes limit exceeded".fmt(f),
+        }
+    }
+}
+
+/// Error returned when an [`Event`][crate::event::Event] cannot be represented on the wire by the [`encoder`][crate::encoder]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The event type contained a CR or LF, which would end the `event:` line early
+    EventContainsNewline,
+    /// The id contained a CR or LF, which would end the `id:` line early
+    IdContainsNewline,
+    /// The id contained a null byte, which parsers are required to ignore
+    IdContainsNull,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EncodeError::EventContainsNewline => "event type contains a line break".fmt(f),
+            EncodeError::IdContainsNewline => "event id contains a line break".fmt(f),
+            EncodeError::IdContainsNull => "event id contains a null byte".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
@@ -0,0 +1,45 @@
+use core::fmt::{Display, Formatter};
+use std::io;
+
+use crate::errors::{DecodeError, EncodeError};
+
+/// Error for [`EventCodec`][crate::codec::EventCodec], covering both directions since the codec is used for both
+#[derive(Debug)]
+pub enum CodecError {
+    /// Reading or writing the underlying IO failed
+    Io(io::Error),
+    /// The incoming bytes couldn't be decoded
+    Decode(DecodeError),
+    /// An outgoing event couldn't be encoded
+    Encode(EncodeError),
+}
+
+impl From<io::Error> for CodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DecodeError> for CodecError {
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<EncodeError> for CodecError {
+    fn from(value: EncodeError) -> Self {
+        Self::Encode(value)
+    }
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CodecError::Io(e) => e.fmt(f),
+            CodecError::Decode(e) => e.fmt(f),
+            CodecError::Encode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for CodecError {}
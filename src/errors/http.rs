@@ -0,0 +1,29 @@
+use core::fmt::{Display, Formatter};
+
+use http::{HeaderValue, StatusCode};
+
+/// Why [`validate_response`][crate::http::validate_response] turned a response away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidResponse {
+    /// The status code wasn't 2xx
+    StatusCode(StatusCode),
+    /// The `Content-Type` header was there but wasn't `text/event-stream`
+    ContentType(HeaderValue),
+}
+
+impl Display for InvalidResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidResponse::StatusCode(status) => write!(f, "got non 2XX status code {status}"),
+            InvalidResponse::ContentType(content_type) => write!(
+                f,
+                "got invalid content-type '{content_type}'",
+                content_type = content_type
+                    .to_str()
+                    .unwrap_or("unable to read content-type as str")
+            ),
+        }
+    }
+}
+
+impl core::error::Error for InvalidResponse {}
@@ -40,3 +40,17 @@ impl EventMut {
         }
     }
 }
+
+/// Item yielded by an [`EventStream`][crate::EventStream] or [`EventStreamBytes`][crate::EventStreamBytes] created with `with_comments`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventOrComment {
+    Event(Event),
+    /// The text of a comment line, after the colon with a single leading space stripped
+    Comment(Str),
+}
+
+impl From<Event> for EventOrComment {
+    fn from(event: Event) -> Self {
+        EventOrComment::Event(event)
+    }
+}
@@ -0,0 +1,1158 @@
+//! Client agnostic [`EventSource`], the reconnecting state machine behind [`crate::reqwest::EventSource`] that can sit
+//! on top of any HTTP client through a [`Connector`]
+
+use core::{
+    error::Error,
+    fmt::{Display, Formatter},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+use std::time::Instant;
+
+use bytes::Bytes;
+use bytes_utils::Str;
+use futures_core::Stream;
+use futures_timer::Delay;
+use http::{HeaderMap, HeaderValue, StatusCode, header::RETRY_AFTER};
+use pin_project_lite::pin_project;
+
+use crate::{
+    constants::EMPTY_STR,
+    errors::{EventStreamError, InvalidResponse},
+    event::{Event, EventOrComment},
+    event_stream::{EmitComments, Limits, Utf8Decoding, bytes::EventStreamBytes},
+    http::validate_response,
+    retry::{DEFAULT_RETRY, ExponentialBackoff, MAX_DELAY, RetryPolicy, RetryReset},
+};
+
+/// A response handed back by a [`Connector`], checked with [`validate_response`] before its body is read
+pub trait Connection {
+    /// Error while reading the body
+    type Error;
+    /// The body as a [`Stream`] of bytes
+    type Body: Stream<Item = Result<Bytes, Self::Error>>;
+
+    /// Status code of the response
+    fn status(&self) -> StatusCode;
+
+    /// Headers of the response
+    fn headers(&self) -> &HeaderMap;
+
+    /// Turn the response into its body
+    fn into_body(self) -> Self::Body;
+}
+
+/// Opens connections for an [`EventSource`], the one thing that changes between HTTP clients
+///
+/// Requests should ask for `text/event-stream` with an `Accept` header and send `last_event_id` as the
+/// `Last-Event-ID` header whenever there is one.
+pub trait Connector {
+    /// Error while connecting, also used for errors reading the body
+    type Error;
+    /// A successful connection
+    type Connection: Connection<Error = Self::Error>;
+    /// Resolves once the response head has arrived
+    type Future: Future<Output = Result<Self::Connection, Self::Error>>;
+
+    /// Start a new connection, `last_event_id` is [None] until an event with an id has been received
+    fn connect(&mut self, last_event_id: Option<&HeaderValue>) -> Self::Future;
+
+    /// Called with every connection that passed [`validate_response`] before its body is read, such as to remember
+    /// where a redirect ended up
+    fn on_open(&mut self, _connection: &Self::Connection) {}
+}
+
+/// Events emitted by [EventSource]
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// A new connection has been opened
+    Open,
+    Event(Event),
+    /// A comment line, only emitted once enabled with [`EventSource::with_comments`]
+    Comment(Str),
+}
+
+impl From<Event> for StreamEvent {
+    fn from(event: Event) -> Self {
+        StreamEvent::Event(event)
+    }
+}
+
+pin_project! {
+    /// [`Stream`] of [`StreamEvent`]s that reconnects through a [`Connector`] according to a [`RetryPolicy`]
+    #[project = EventSourceProjection]
+    #[derive(Debug)]
+    pub struct EventSource<C, R>
+    where
+        C: Connector,
+    {
+        connector: C,
+        #[pin]
+        connection_state: ConnectionState<C>,
+        last_event_id: Str,
+        retry_policy: R,
+        emit_comments: bool,
+        stop_on_fatal: bool,
+        idle_timeout: Option<Duration>,
+        retry_reset: RetryReset,
+        limits: Limits,
+        utf8: Utf8Decoding,
+    }
+}
+
+pin_project! {
+    /// Wraps the response body so that going too long without receiving any bytes shows up as an error
+    #[derive(Debug)]
+    struct IdleTimeout<S> {
+        #[pin]
+        stream: S,
+        #[pin]
+        delay: Option<Delay>,
+        timeout: Duration,
+    }
+}
+
+impl<S> IdleTimeout<S> {
+    fn new(stream: S, timeout: Option<Duration>) -> Self {
+        Self {
+            stream,
+            delay: timeout.map(Delay::new),
+            timeout: timeout.unwrap_or_default(),
+        }
+    }
+}
+
+/// Error from the response body, [`BodyError::IdleTimeout`] gets pulled back out into [`EventSourceErrorKind::IdleTimeout`]
+#[derive(Debug)]
+enum BodyError<E> {
+    Transport(E),
+    IdleTimeout,
+}
+
+impl<S, E> Stream for IdleTimeout<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    type Item = Result<Bytes, BodyError<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(Ok(bytes))) => {
+                if let Some(delay) = this.delay.as_pin_mut() {
+                    delay.get_mut().reset(*this.timeout);
+                }
+                Poll::Ready(Some(Ok(bytes)))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(BodyError::Transport(e)))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match this.delay.as_pin_mut() {
+                Some(delay) => {
+                    ready!(delay.poll(cx));
+                    Poll::Ready(Some(Err(BodyError::IdleTimeout)))
+                }
+                None => Poll::Pending,
+            },
+        }
+    }
+}
+
+pin_project! {
+    /// Comments are only decoded when they're going to be emitted, otherwise they're skipped without being validated
+    #[project = BodyStreamProjection]
+    #[derive(Debug)]
+    enum BodyStream<B> {
+        SkipComments {
+            #[pin]
+            stream: EventStreamBytes<IdleTimeout<B>>,
+        },
+        EmitComments {
+            #[pin]
+            stream: EventStreamBytes<IdleTimeout<B>, EmitComments>,
+        },
+    }
+}
+
+impl<B, E> Stream for BodyStream<B>
+where
+    B: Stream<Item = Result<Bytes, E>>,
+{
+    type Item = Result<EventOrComment, EventStreamError<BodyError<E>>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.project() {
+            BodyStreamProjection::SkipComments { stream } => stream
+                .poll_next(cx)
+                .map(|item| item.map(|item| item.map(EventOrComment::Event))),
+            BodyStreamProjection::EmitComments { stream } => stream.poll_next(cx),
+        }
+    }
+}
+
+type BodyOf<C> = <<C as Connector>::Connection as Connection>::Body;
+
+pin_project! {
+    #[project = ConnectionStateProjection]
+    enum ConnectionState<C>
+    where
+        C: Connector,
+    {
+        Connecting {
+            #[pin]
+            future: C::Future,
+            retry_state: Option<(usize, Duration)>,
+        },
+        Retrying {
+            #[pin]
+            delay: Delay,
+            attempt_number: usize,
+            delay_duration: Duration,
+        },
+        Open {
+            #[pin]
+            stream: BodyStream<BodyOf<C>>,
+            retry_state: Option<(usize, Duration)>,
+            opened_at: Instant,
+        },
+        Closed,
+    }
+}
+
+impl<C> core::fmt::Debug for ConnectionState<C>
+where
+    C: Connector,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Connecting { retry_state, .. } => f
+                .debug_struct("Connecting")
+                .field("future", &"future")
+                .field("retry_state", retry_state)
+                .finish(),
+            Self::Retrying {
+                delay,
+                attempt_number,
+                delay_duration,
+            } => f
+                .debug_struct("Retrying")
+                .field("delay", delay)
+                .field("attempt_number", attempt_number)
+                .field("delay_duration", delay_duration)
+                .finish(),
+            Self::Open {
+                retry_state,
+                opened_at,
+                ..
+            } => f
+                .debug_struct("Open")
+                .field("stream", &"stream")
+                .field("retry_state", retry_state)
+                .field("opened_at", opened_at)
+                .finish(),
+            Self::Closed => write!(f, "Closed"),
+        }
+    }
+}
+
+/// [`EventSourceErrorKind`] for the types of a [`Connector`]
+type ErrorKindOf<C> = EventSourceErrorKind<<C as Connector>::Error, <C as Connector>::Connection>;
+
+impl<'pin, C, R> EventSourceProjection<'pin, C, R>
+where
+    C: Connector,
+{
+    fn initiate_connection(
+        &mut self,
+        retry_state: Option<(usize, Duration)>,
+    ) -> Result<(), ErrorKindOf<C>> {
+        let last_event_id = if self.last_event_id.is_empty() {
+            None
+        } else {
+            Some(HeaderValue::from_str(self.last_event_id).map_err(|_| {
+                EventSourceErrorKind::InvalidLastEventId(self.last_event_id.clone())
+            })?)
+        };
+        let future = self.connector.connect(last_event_id.as_ref());
+        self.connection_state.set(ConnectionState::Connecting {
+            future,
+            retry_state,
+        });
+        Ok(())
+    }
+
+    fn handle_successful_response(
+        &mut self,
+        connection: C::Connection,
+        retry_state: Option<(usize, Duration)>,
+    ) -> Result<(), ErrorKindOf<C>> {
+        let status = connection.status();
+        match validate_response(status, connection.headers()) {
+            Ok(()) => {}
+            Err(InvalidResponse::StatusCode(status)) => {
+                return Err(EventSourceErrorKind::InvalidStatusCode {
+                    status,
+                    response: Box::new(connection),
+                });
+            }
+            Err(InvalidResponse::ContentType(content_type)) => {
+                return Err(EventSourceErrorKind::InvalidContentType {
+                    status,
+                    content_type,
+                    response: Box::new(connection),
+                });
+            }
+        }
+
+        self.connector.on_open(&connection);
+        let mut stream =
+            EventStreamBytes::new(IdleTimeout::new(connection.into_body(), *self.idle_timeout))
+                .with_limits(*self.limits)
+                .with_utf8_decoding(*self.utf8);
+        // events without an id keep the one from before the reconnect
+        stream.set_last_event_id(self.last_event_id.clone());
+        let stream = if *self.emit_comments {
+            BodyStream::EmitComments {
+                stream: stream.with_comments(),
+            }
+        } else {
+            BodyStream::SkipComments { stream }
+        };
+        self.connection_state.set(ConnectionState::Open {
+            stream,
+            retry_state,
+            opened_at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Wait `wait` before reconnecting, `delay_duration` is the policy's own delay handed back as the last retry
+    fn start_retry(&mut self, attempt_number: usize, wait: Duration, delay_duration: Duration) {
+        self.connection_state.set(ConnectionState::Retrying {
+            delay: Delay::new(wait.min(MAX_DELAY)),
+            attempt_number,
+            delay_duration,
+        })
+    }
+
+    fn handle_error(&mut self, err: &ErrorKindOf<C>, last_retry: Option<(usize, Duration)>)
+    where
+        R: RetryPolicy<ErrorKindOf<C>>,
+    {
+        if *self.stop_on_fatal && !err.is_retryable() {
+            self.connection_state.set(ConnectionState::Closed);
+            return;
+        }
+        // the server's delay is only waited out, the backoff carries on from the policy's own delay
+        let retry_delay = match err.retry_after() {
+            Some(retry_after) => self
+                .retry_policy
+                .retry_after(err, last_retry, retry_after)
+                .map(|delay| (delay.max(retry_after), delay)),
+            None => self
+                .retry_policy
+                .retry(err, last_retry)
+                .map(|delay| (delay, delay)),
+        };
+        if let Some((wait, delay_duration)) = retry_delay {
+            let retry_num = last_retry.map(|retry| retry.0).unwrap_or(1);
+            self.start_retry(retry_num, wait, delay_duration);
+        } else {
+            self.connection_state.set(ConnectionState::Closed);
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event)
+    where
+        R: RetryPolicy<ErrorKindOf<C>>,
+    {
+        *self.last_event_id = event.id.clone();
+        if let Some(duration) = event.retry {
+            self.retry_policy.set_reconnection_time(duration)
+        }
+    }
+}
+
+impl<C> EventSource<C, ExponentialBackoff>
+where
+    C: Connector,
+{
+    /// Create a new [`EventSource`] on top of `connector` with the default [`ExponentialBackoff`], the first
+    /// connection is started straight away
+    ///
+    /// Like a browser this closes the source on responses that won't get better by retrying, see
+    /// [`EventSourceErrorKind::is_retryable`]. Everything else, including 429 and 5XX responses, is retried forever.
+    pub fn from_connector(connector: C) -> Self {
+        let mut source = Self::from_connector_with_retry(connector, DEFAULT_RETRY);
+        source.stop_on_fatal = true;
+        source
+    }
+}
+
+impl<C, R> EventSource<C, R>
+where
+    C: Connector,
+{
+    /// Create a new [`EventSource`] on top of `connector` that reconnects according to `retry_policy`, the first
+    /// connection is started straight away
+    ///
+    /// Every error is handed to the policy, including failing to connect, non 2XX responses and bad content types,
+    /// so inspect the [`EventSourceErrorKind`] if some of those should close the source instead.
+    pub fn from_connector_with_retry(mut connector: C, retry_policy: R) -> Self {
+        let future = connector.connect(None);
+        EventSource {
+            connector,
+            connection_state: ConnectionState::Connecting {
+                future,
+                retry_state: None,
+            },
+            last_event_id: EMPTY_STR,
+            retry_policy,
+            emit_comments: false,
+            stop_on_fatal: false,
+            idle_timeout: None,
+            retry_reset: RetryReset::Never,
+            limits: Limits::new(),
+            utf8: Utf8Decoding::Strict,
+        }
+    }
+
+    /// Reference to the [`Connector`] this source opens connections with
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    /// Reference to the id of the last event received, sent as `Last-Event-ID` when reconnecting
+    pub fn last_event_id(&self) -> &Str {
+        &self.last_event_id
+    }
+
+    /// Reconnect if no bytes at all are received for `timeout`, comments count so `: ping` heartbeats keep the connection alive.
+    /// The current connection is dropped, a [`EventSourceError::is_idle_timeout`] error is emitted and the [`RetryPolicy`] decides
+    /// whether to reconnect.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Choose when to forget about earlier retries, see [`RetryReset`]. Defaults to [`RetryReset::Never`].
+    pub fn with_retry_reset(mut self, retry_reset: RetryReset) -> Self {
+        self.retry_reset = retry_reset;
+        self
+    }
+
+    /// Cap how much each connection's stream buffers, see [`Limits`]. A limit set to
+    /// [error out][crate::event_stream::OnLimitExceeded::Error] drops the connection and the [`RetryPolicy`] decides whether
+    /// to reconnect.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Choose how the body is decoded into text, see [`Utf8Decoding`]. Defaults to [`Utf8Decoding::Strict`] where a line
+    /// of invalid UTF-8 is an error, [`Utf8Decoding::Lossy`] keeps the connection going as browsers do.
+    pub fn with_utf8_decoding(mut self, utf8: Utf8Decoding) -> Self {
+        self.utf8 = utf8;
+        self
+    }
+
+    /// Yield comment lines as [`StreamEvent::Comment`], useful for tracking `: ping` style heartbeats. Comments are skipped by default.
+    pub fn with_comments(mut self) -> Self {
+        self.emit_comments = true;
+        self
+    }
+}
+
+/// What went wrong in an [`EventSource`], handed to the [`RetryPolicy`] to decide whether to reconnect
+///
+/// `E` is the [`Connector::Error`] and `T` the [`Connector::Connection`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EventSourceErrorKind<E, T> {
+    /// The last event ID contains non-visible ascii characters, unusable in a [`HeaderValue`]
+    InvalidLastEventId(Str),
+    /// The [`Connector`] has had an error when getting a response
+    Transport(E),
+    /// The underlying stream has ran into an error
+    Stream(EventStreamError<E>),
+    /// Received a [non 2xx][StatusCode::is_success] response
+    InvalidStatusCode {
+        status: StatusCode,
+        response: Box<T>, // boxed because this was a big error
+    },
+    /// Received a 2XX status, but the [Content-Type][http::header::CONTENT_TYPE] was not "text/event-stream"
+    InvalidContentType {
+        status: StatusCode,
+        content_type: HeaderValue,
+        response: Box<T>,
+    },
+    /// The underlying stream has ran to completion
+    StreamEnded, // not sure how i feel about this being an error tbh, change me?
+    /// No bytes were received for the configured [idle timeout][EventSource::with_idle_timeout]
+    IdleTimeout(Duration),
+}
+
+impl<E, T> Display for EventSourceErrorKind<E, T>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EventSourceErrorKind::InvalidLastEventId(s) => s.fmt(f),
+            EventSourceErrorKind::Transport(err) => err.fmt(f),
+            EventSourceErrorKind::Stream(err) => err.fmt(f),
+            EventSourceErrorKind::InvalidStatusCode { status, .. } => write!(
+                f,
+                "got non 2XX status code {status}: '{canonical}'",
+                canonical = status.canonical_reason().unwrap_or("no canonical reason")
+            ),
+            EventSourceErrorKind::InvalidContentType {
+                status,
+                content_type,
+                ..
+            } => write!(
+                f,
+                "got invalid content-type '{content_type}' on status '{status}' request",
+                content_type = content_type
+                    .to_str()
+                    .unwrap_or("unable to read content-type as str")
+            ),
+            EventSourceErrorKind::StreamEnded => "stream ended".fmt(f),
+            EventSourceErrorKind::IdleTimeout(timeout) => {
+                write!(f, "no data received for {timeout:?}")
+            }
+        }
+    }
+}
+
+impl<E, T> Error for EventSourceErrorKind<E, T>
+where
+    E: core::fmt::Debug + Display,
+    T: core::fmt::Debug,
+{
+}
+
+/// Error from an [`EventSource`], an [`EventSourceErrorKind`] along with the retry it happened after
+#[derive(Debug)]
+pub struct EventSourceError<E, T> {
+    retry_state: Option<(usize, Duration)>,
+    kind: EventSourceErrorKind<E, T>,
+}
+
+impl<E, T> Display for EventSourceError<E, T>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let retry_state = self
+            .retry_state
+            .as_ref()
+            .map(|(attempts, duration)| format!("attempts: '{attempts}' duration: {duration:?}"))
+            .unwrap_or(String::from("no last retry"));
+        write!(
+            f,
+            "Error '{kind}' with last retry: {retry_state}",
+            kind = self.kind
+        )
+    }
+}
+
+impl<E, T> Error for EventSourceError<E, T>
+where
+    E: core::fmt::Debug + Display,
+    T: core::fmt::Debug,
+{
+}
+
+impl<E, T> EventSourceError<E, T> {
+    fn new(
+        kind: impl Into<EventSourceErrorKind<E, T>>,
+        retry_state: impl Into<Option<(usize, Duration)>>,
+    ) -> Self {
+        Self {
+            retry_state: retry_state.into(),
+            kind: kind.into(),
+        }
+    }
+
+    /// The kind of error, useful for matching on or passing to a [`RetryPolicy`]
+    pub fn kind(&self) -> &EventSourceErrorKind<E, T> {
+        &self.kind
+    }
+
+    /// Take the kind of error out of this error
+    pub fn into_kind(self) -> EventSourceErrorKind<E, T> {
+        self.kind
+    }
+
+    /// The attempt number and delay of the last retry, [None] if this error came before any retries
+    pub fn retry_state(&self) -> Option<(usize, Duration)> {
+        self.retry_state
+    }
+
+    /// Was this error caused by [Connection::status] being a non 2XX
+    pub fn is_status_code(&self) -> bool {
+        self.kind.is_status_code()
+    }
+
+    /// Was this error caused by [Content-Type][http::header::CONTENT_TYPE] not being "text/event-stream"
+    pub fn is_content_type(&self) -> bool {
+        self.kind.is_content_type()
+    }
+
+    /// Was the error caused by an invalid response
+    pub fn is_response_err(&self) -> bool {
+        self.kind.is_response_err()
+    }
+
+    /// Was this error caused by the network, either while connecting or while reading the body
+    pub fn is_transport(&self) -> bool {
+        self.kind.is_transport()
+    }
+
+    /// Get the status code that caused this error, if it was caused by an invalid response
+    pub fn status_code(&self) -> Option<StatusCode> {
+        self.kind.status_code()
+    }
+
+    /// Gets a reference to the underlying response if this error was caused by an invalid response
+    pub fn response(&self) -> Option<&T> {
+        self.kind.response()
+    }
+
+    /// If this error comes from a response, return the raw response, collected status code and the [Content-Type][http::header::CONTENT_TYPE] header if it has been extracted already
+    pub fn into_response_err(self) -> Option<(T, StatusCode, Option<HeaderValue>)> {
+        self.kind.into_response_err()
+    }
+
+    /// Could reconnecting fix this error, see [`EventSourceErrorKind::is_retryable`]
+    pub fn is_retryable(&self) -> bool {
+        self.kind.is_retryable()
+    }
+
+    /// Is this error because the stream has stopped?
+    pub fn is_stream_ended(&self) -> bool {
+        self.kind.is_stream_ended()
+    }
+
+    /// Is this error because no data was received within the [idle timeout][EventSource::with_idle_timeout]?
+    pub fn is_idle_timeout(&self) -> bool {
+        self.kind.is_idle_timeout()
+    }
+}
+
+impl<E, T> EventSourceError<E, T>
+where
+    T: Connection,
+{
+    /// Gets the raw [Retry-After][RETRY_AFTER] header if this error was caused by an invalid response that had one
+    pub fn retry_after_header(&self) -> Option<&HeaderValue> {
+        self.kind.retry_after_header()
+    }
+
+    /// How long the server asked us to wait via the [Retry-After][RETRY_AFTER] header, see [`EventSourceErrorKind::retry_after`]
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.kind.retry_after()
+    }
+}
+
+impl<E, T> EventSourceErrorKind<E, T> {
+    /// Was this error caused by [Connection::status] being a non 2XX
+    pub fn is_status_code(&self) -> bool {
+        matches!(self, EventSourceErrorKind::InvalidStatusCode { .. })
+    }
+
+    /// Was this error caused by [Content-Type][http::header::CONTENT_TYPE] not being "text/event-stream"
+    pub fn is_content_type(&self) -> bool {
+        matches!(self, EventSourceErrorKind::InvalidContentType { .. })
+    }
+
+    /// Was the error caused by an invalid response
+    pub fn is_response_err(&self) -> bool {
+        matches!(
+            self,
+            EventSourceErrorKind::InvalidContentType { .. }
+                | EventSourceErrorKind::InvalidStatusCode { .. }
+        )
+    }
+
+    /// Was this error caused by the network, either while connecting or while reading the body
+    pub fn is_transport(&self) -> bool {
+        matches!(
+            self,
+            EventSourceErrorKind::Transport(_)
+                | EventSourceErrorKind::Stream(EventStreamError::Transport(_))
+        )
+    }
+
+    /// Get the status code that caused this error, if it was caused by an invalid response
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            EventSourceErrorKind::InvalidStatusCode { status, .. }
+            | EventSourceErrorKind::InvalidContentType { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Gets a reference to the underlying response if this error was caused by an invalid response
+    pub fn response(&self) -> Option<&T> {
+        match self {
+            EventSourceErrorKind::InvalidStatusCode { response, .. }
+            | EventSourceErrorKind::InvalidContentType { response, .. } => Some(response),
+
+            _ => None,
+        }
+    }
+
+    /// If this error comes from a response, return the raw response, collected status code and the [Content-Type][http::header::CONTENT_TYPE] header if it has been extracted already
+    pub fn into_response_err(self) -> Option<(T, StatusCode, Option<HeaderValue>)> {
+        match self {
+            EventSourceErrorKind::InvalidStatusCode { response, status } => {
+                Some((*response, status, None))
+            }
+            EventSourceErrorKind::InvalidContentType {
+                status,
+                content_type,
+                response,
+            } => Some((*response, status, Some(content_type))),
+            _ => None,
+        }
+    }
+
+    /// Could reconnecting fix this error, false for responses other than 429 and 5XX along with any response with the
+    /// wrong content type. Use it with [`RetryPolicyExt::only_if`][crate::retry::RetryPolicyExt::only_if] to give a
+    /// custom policy the same behaviour as [`EventSource::from_connector`].
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            EventSourceErrorKind::InvalidStatusCode { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            EventSourceErrorKind::InvalidContentType { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Is this error because the stream has stopped?
+    pub fn is_stream_ended(&self) -> bool {
+        matches!(self, EventSourceErrorKind::StreamEnded)
+    }
+
+    /// Is this error because no data was received within the [idle timeout][EventSource::with_idle_timeout]?
+    pub fn is_idle_timeout(&self) -> bool {
+        matches!(self, EventSourceErrorKind::IdleTimeout(_))
+    }
+
+    fn from_body_error(err: EventStreamError<BodyError<E>>, idle_timeout: Duration) -> Self {
+        match err {
+            EventStreamError::Transport(BodyError::Transport(e)) => {
+                Self::Stream(EventStreamError::Transport(e))
+            }
+            EventStreamError::Transport(BodyError::IdleTimeout) => Self::IdleTimeout(idle_timeout),
+            EventStreamError::Utf8Error(e) => Self::Stream(EventStreamError::Utf8Error(e)),
+            EventStreamError::LimitExceeded(e) => Self::Stream(EventStreamError::LimitExceeded(e)),
+        }
+    }
+}
+
+impl<E, T> EventSourceErrorKind<E, T>
+where
+    T: Connection,
+{
+    /// Gets the raw [Retry-After][RETRY_AFTER] header if this error was caused by an invalid response that had one
+    pub fn retry_after_header(&self) -> Option<&HeaderValue> {
+        self.response()?.headers().get(RETRY_AFTER)
+    }
+
+    /// How long the server asked us to wait via the [Retry-After][RETRY_AFTER] header, usually sent with a 429 or 503.
+    /// Dates in the past give a zero [`Duration`] and anything past [`MAX_DELAY`] is cut down to it, [None] if there is
+    /// no header or it can't be parsed.
+    pub fn retry_after(&self) -> Option<Duration> {
+        parse_retry_after(self.retry_after_header()?)
+    }
+}
+
+/// Parses either form of [Retry-After][RETRY_AFTER], delta-seconds or an HTTP-date
+pub(crate) fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_DELAY))
+}
+
+impl<E, T> From<E> for EventSourceErrorKind<E, T> {
+    fn from(value: E) -> Self {
+        Self::Transport(value)
+    }
+}
+
+impl<E, T> From<EventStreamError<E>> for EventSourceErrorKind<E, T> {
+    fn from(value: EventStreamError<E>) -> Self {
+        Self::Stream(value)
+    }
+}
+
+impl<C, R> Stream for EventSource<C, R>
+where
+    C: Connector,
+    R: RetryPolicy<ErrorKindOf<C>>,
+{
+    type Item = Result<StreamEvent, EventSourceError<C::Error, C::Connection>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            match this.connection_state.as_mut().project() {
+                ConnectionStateProjection::Connecting {
+                    future,
+                    retry_state,
+                } => {
+                    let retry_state = *retry_state;
+                    let result = match ready!(future.poll(cx)) {
+                        Ok(connection) if connection.status() == StatusCode::NO_CONTENT => {
+                            // the spec says a 204 means the server wants us to stop reconnecting
+                            this.connection_state.set(ConnectionState::Closed);
+                            return Poll::Ready(None);
+                        }
+                        Ok(connection) => this.handle_successful_response(connection, retry_state),
+                        Err(err) => Err(EventSourceErrorKind::Transport(err)),
+                    };
+                    // connect errors, bad statuses and bad content types all go through the retry policy
+                    match result {
+                        Ok(()) => return Poll::Ready(Some(Ok(StreamEvent::Open))),
+                        Err(err_kind) => {
+                            this.handle_error(&err_kind, retry_state);
+                            return Poll::Ready(Some(Err(EventSourceError::new(
+                                err_kind,
+                                retry_state,
+                            ))));
+                        }
+                    }
+                }
+                ConnectionStateProjection::Retrying {
+                    delay,
+                    attempt_number,
+                    delay_duration,
+                } => {
+                    ready!(delay.poll(cx));
+                    let retry_state = Some((*attempt_number + 1, *delay_duration));
+                    if let Err(err) = this.initiate_connection(retry_state) {
+                        this.connection_state.set(ConnectionState::Closed);
+                        return Poll::Ready(Some(Err(EventSourceError::new(err, retry_state))));
+                    }
+                }
+                ConnectionStateProjection::Open {
+                    stream,
+                    retry_state,
+                    opened_at,
+                } => {
+                    let item = ready!(stream.poll_next(cx));
+                    let healthy = match *this.retry_reset {
+                        RetryReset::Never => false,
+                        RetryReset::OnEvent => {
+                            matches!(item, Some(Ok(EventOrComment::Event(_))))
+                        }
+                        RetryReset::AfterOpenFor(threshold) => opened_at.elapsed() >= threshold,
+                    };
+                    if healthy {
+                        *retry_state = None;
+                    }
+
+                    let retry_state = *retry_state;
+                    match item {
+                        Some(Ok(EventOrComment::Event(event))) => {
+                            this.handle_event(&event);
+                            return Poll::Ready(Some(Ok(event.into())));
+                        }
+                        Some(Ok(EventOrComment::Comment(comment))) => {
+                            return Poll::Ready(Some(Ok(StreamEvent::Comment(comment))));
+                        }
+                        Some(Err(err)) => {
+                            let err_kind = EventSourceErrorKind::from_body_error(
+                                err,
+                                this.idle_timeout.unwrap_or_default(),
+                            );
+                            this.handle_error(&err_kind, retry_state);
+                            return Poll::Ready(Some(Err(EventSourceError::new(
+                                err_kind,
+                                retry_state,
+                            ))));
+                        }
+                        None => {
+                            let err_kind = EventSourceErrorKind::StreamEnded;
+                            this.handle_error(&err_kind, retry_state);
+                            return Poll::Ready(Some(Err(EventSourceError::new(
+                                err_kind,
+                                retry_state,
+                            ))));
+                        }
+                    }
+                }
+                ConnectionStateProjection::Closed => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::{Constant, Jitter, JitteredBackoff, Never};
+    use core::future::{Ready, ready};
+    use futures::{StreamExt, stream};
+    use http::header::CONTENT_TYPE;
+    use std::{collections::VecDeque, vec::IntoIter};
+
+    /// Canned response for [`MemoryConnector`]
+    struct MemoryConnection {
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Vec<&'static [u8]>,
+    }
+
+    impl Connection for MemoryConnection {
+        type Error = &'static str;
+        type Body = stream::Iter<IntoIter<Result<Bytes, &'static str>>>;
+
+        fn status(&self) -> StatusCode {
+            self.status
+        }
+
+        fn headers(&self) -> &HeaderMap {
+            &self.headers
+        }
+
+        fn into_body(self) -> Self::Body {
+            let chunks: Vec<_> = self
+                .body
+                .into_iter()
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect();
+            stream::iter(chunks)
+        }
+    }
+
+    /// Hands out one canned connection per attempt and records the last event id of every attempt
+    #[derive(Default)]
+    struct MemoryConnector {
+        connections: VecDeque<Result<MemoryConnection, &'static str>>,
+        last_event_ids: Vec<Option<HeaderValue>>,
+    }
+
+    impl Connector for MemoryConnector {
+        type Error = &'static str;
+        type Connection = MemoryConnection;
+        type Future = Ready<Result<MemoryConnection, &'static str>>;
+
+        fn connect(&mut self, last_event_id: Option<&HeaderValue>) -> Self::Future {
+            self.last_event_ids.push(last_event_id.cloned());
+            ready(
+                self.connections
+                    .pop_front()
+                    .unwrap_or(Err("no more connections")),
+            )
+        }
+    }
+
+    fn ok(body: Vec<&'static [u8]>) -> Result<MemoryConnection, &'static str> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+        Ok(MemoryConnection {
+            status: StatusCode::OK,
+            headers,
+            body,
+        })
+    }
+
+    fn status(status: StatusCode) -> Result<MemoryConnection, &'static str> {
+        Ok(MemoryConnection {
+            status,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn connector_reconnects() {
+        let connector = MemoryConnector {
+            connections: VecDeque::from([
+                Err("refused"),
+                status(StatusCode::SERVICE_UNAVAILABLE),
+                ok(vec![b"id: 1\ndata: hel", b"lo\n\n"]),
+                ok(vec![b"data: again\n\n"]),
+                status(StatusCode::NO_CONTENT),
+            ]),
+            ..Default::default()
+        };
+        let source = EventSource::from_connector_with_retry(
+            connector,
+            Constant::new(Duration::from_millis(1), None),
+        );
+        futures::pin_mut!(source);
+
+        assert!(source.next().await.unwrap().unwrap_err().is_transport());
+        assert!(source.next().await.unwrap().unwrap_err().is_status_code());
+        assert!(matches!(source.next().await, Some(Ok(StreamEvent::Open))));
+        assert!(matches!(
+            source.next().await,
+            Some(Ok(StreamEvent::Event(event))) if event.data == "hello"
+        ));
+        assert!(source.next().await.unwrap().unwrap_err().is_stream_ended());
+        assert!(matches!(source.next().await, Some(Ok(StreamEvent::Open))));
+        assert!(matches!(
+            source.next().await,
+            Some(Ok(StreamEvent::Event(event))) if event.data == "again"
+        ));
+        assert!(source.next().await.unwrap().unwrap_err().is_stream_ended());
+        assert!(source.next().await.is_none());
+
+        assert_eq!(*source.last_event_id(), "1");
+        assert_eq!(
+            source.connector().last_event_ids,
+            vec![
+                None,
+                None,
+                None,
+                Some(HeaderValue::from_static("1")),
+                Some(HeaderValue::from_static("1")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn skipped_comments_are_not_validated() {
+        let connector = MemoryConnector {
+            connections: VecDeque::from([ok(vec![b": \xFF\ndata: ok\n\n"])]),
+            ..Default::default()
+        };
+        let source = EventSource::from_connector_with_retry(connector, Never);
+        futures::pin_mut!(source);
+
+        assert!(matches!(source.next().await, Some(Ok(StreamEvent::Open))));
+        assert!(matches!(
+            source.next().await,
+            Some(Ok(StreamEvent::Event(event))) if event.data == "ok"
+        ));
+        assert!(source.next().await.unwrap().unwrap_err().is_stream_ended());
+        assert!(source.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn default_policy_stops_on_fatal_responses() {
+        let connector = MemoryConnector {
+            connections: VecDeque::from([
+                status(StatusCode::TOO_MANY_REQUESTS),
+                status(StatusCode::NOT_FOUND),
+                ok(vec![b"data: unreachable\n\n"]),
+            ]),
+            ..Default::default()
+        };
+        let source = EventSource::from_connector(connector);
+        futures::pin_mut!(source);
+
+        let err = source.next().await.unwrap().unwrap_err();
+        assert!(err.is_retryable());
+        let err = source.next().await.unwrap().unwrap_err();
+        assert_eq!(err.status_code(), Some(StatusCode::NOT_FOUND));
+        assert!(!err.is_retryable());
+        assert!(source.next().await.is_none());
+        assert_eq!(source.connector().last_event_ids.len(), 2);
+    }
+
+    /// Which method was called and the last retry it was given
+    type Call = (&'static str, Option<(usize, Duration)>);
+
+    /// Retries straight away, recording every call
+    #[derive(Default)]
+    struct Recording {
+        calls: std::rc::Rc<core::cell::RefCell<Vec<Call>>>,
+    }
+
+    impl<E> RetryPolicy<E> for Recording {
+        fn retry(&self, _err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+            self.calls.borrow_mut().push(("retry", last_retry));
+            Some(Duration::from_millis(1))
+        }
+
+        fn retry_after(
+            &self,
+            _err: &E,
+            last_retry: Option<(usize, Duration)>,
+            _retry_after: Duration,
+        ) -> Option<Duration> {
+            self.calls.borrow_mut().push(("retry_after", last_retry));
+            Some(Duration::from_millis(1))
+        }
+
+        fn set_reconnection_time(&mut self, _duration: Duration) {}
+    }
+
+    #[tokio::test]
+    async fn retry_after_asks_the_policy_once() {
+        let mut rate_limited = status(StatusCode::TOO_MANY_REQUESTS).unwrap();
+        rate_limited
+            .headers
+            .insert(RETRY_AFTER, HeaderValue::from_static("0"));
+        let connector = MemoryConnector {
+            connections: VecDeque::from([
+                Ok(rate_limited),
+                status(StatusCode::SERVICE_UNAVAILABLE),
+            ]),
+            ..Default::default()
+        };
+        let policy = Recording::default();
+        let calls = policy.calls.clone();
+        let source = EventSource::from_connector_with_retry(connector, policy);
+        futures::pin_mut!(source);
+
+        assert!(source.next().await.unwrap().unwrap_err().is_status_code());
+        assert!(source.next().await.unwrap().unwrap_err().is_status_code());
+        assert_eq!(
+            calls.borrow()[..2],
+            [
+                ("retry_after", None),
+                ("retry", Some((2, Duration::from_millis(1)))),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn full_jitter_steps_up_from_start() {
+        let connector = MemoryConnector {
+            connections: VecDeque::from([
+                status(StatusCode::SERVICE_UNAVAILABLE),
+                status(StatusCode::SERVICE_UNAVAILABLE),
+                status(StatusCode::SERVICE_UNAVAILABLE),
+                status(StatusCode::SERVICE_UNAVAILABLE),
+            ]),
+            ..Default::default()
+        };
+        // always picks the top of the range so each delay sits just under its ceiling
+        let policy = JitteredBackoff::new(
+            Duration::from_millis(1),
+            2.,
+            None,
+            None,
+            Jitter::Full,
+            || u64::MAX,
+        );
+        let source = EventSource::from_connector_with_retry(connector, policy);
+        futures::pin_mut!(source);
+
+        assert_eq!(
+            source.next().await.unwrap().unwrap_err().retry_state(),
+            None
+        );
+        for ceiling in [1, 2, 4] {
+            let err = source.next().await.unwrap().unwrap_err();
+            let (_, delay) = err.retry_state().unwrap();
+            assert!(delay > Duration::from_micros(ceiling * 1000 / 2));
+            assert!(delay <= Duration::from_millis(ceiling));
+        }
+    }
+}
@@ -2,16 +2,143 @@
 
 use core::time::Duration;
 
-use ::bytes::{BufMut, BytesMut};
+use core::str::Utf8Error;
+
+use ::bytes::{Buf, BufMut, Bytes, BytesMut};
 use bytes_utils::{Str, StrMut};
 
 use crate::{
-    constants::{BOM, EMPTY_STR, MESSAGE_STR},
-    errors::EventStreamError,
-    event::Event,
-    parser::{FieldName, RawEventLineOwned, ValidatedEventLine, parse_line_from_buffer},
+    constants::{BOM, CR, EMPTY_STR, MESSAGE_STR},
+    errors::{DecodeError, LimitExceeded},
+    event::{Event, EventOrComment},
+    parser::{
+        FieldName, RawEventLineOwned, ValidatedEventLine, parse_line_from_buffer,
+        parse_line_from_bytes, validate_bytes, validate_bytes_lossy,
+    },
 };
 
+/// Decides what an event stream does with comment lines, and so what its items are
+pub trait CommentMode {
+    type Item: From<Event>;
+
+    /// Turn the text of a comment line into an item, or [None] to skip it. `utf8` is how the stream was asked to decode
+    /// text.
+    fn comment(comment: Bytes, utf8: Utf8Decoding) -> Result<Option<Self::Item>, Utf8Error>;
+}
+
+/// The default [`CommentMode`], comments are thrown away without being validated and the stream yields [`Event`]s
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkipComments;
+
+impl CommentMode for SkipComments {
+    type Item = Event;
+
+    #[inline]
+    fn comment(_comment: Bytes, _utf8: Utf8Decoding) -> Result<Option<Self::Item>, Utf8Error> {
+        Ok(None)
+    }
+}
+
+/// [`CommentMode`] that yields comments alongside events as [`EventOrComment`]s, useful for watching `: ping` style heartbeats
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmitComments;
+
+impl CommentMode for EmitComments {
+    type Item = EventOrComment;
+
+    #[inline]
+    fn comment(comment: Bytes, utf8: Utf8Decoding) -> Result<Option<Self::Item>, Utf8Error> {
+        utf8.decode(comment)
+            .map(|comment| Some(EventOrComment::Comment(comment)))
+    }
+}
+
+/// How an event stream turns bytes into text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Utf8Decoding {
+    /// Yield [`EventStreamError::Utf8Error`][crate::errors::EventStreamError::Utf8Error] for any line that isn't valid UTF-8, the stream carries on from the next line
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD like the spec says to. Lines that are already valid aren't copied.
+    Lossy,
+}
+
+impl Utf8Decoding {
+    /// Decode `bytes` into a [`Str`], only fails in [`Utf8Decoding::Strict`]
+    pub fn decode(self, bytes: Bytes) -> Result<Str, Utf8Error> {
+        match self {
+            Utf8Decoding::Strict => validate_bytes(bytes),
+            Utf8Decoding::Lossy => Ok(validate_bytes_lossy(bytes)),
+        }
+    }
+}
+
+/// Everything that changes how a stream parses, kept together so it can be handed down in one go
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ParseOptions {
+    pub(crate) limits: Limits,
+    pub(crate) utf8: Utf8Decoding,
+}
+
+impl ParseOptions {
+    pub(crate) const fn new() -> Self {
+        Self {
+            limits: Limits::new(),
+            utf8: Utf8Decoding::Strict,
+        }
+    }
+}
+
+/// Caps on how much an event stream will buffer, so a server that never ends a line or an event can't use up all our
+/// memory
+///
+/// Every limit is off by default.
+///
+/// ```rust
+/// use sseer::event_stream::{Limits, OnLimitExceeded};
+///
+/// let limits = Limits {
+///     max_line_len: Some(64 * 1024),
+///     max_event_data: Some(1024 * 1024),
+///     on_exceeded: OnLimitExceeded::SkipEvent,
+///     ..Limits::new()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest line allowed in bytes, counting its line ending
+    pub max_line_len: Option<usize>,
+    /// Largest `data` allowed for a single event in bytes, counting the newlines that join multiple `data` lines
+    pub max_event_data: Option<usize>,
+    /// Most bytes held at once for the event in progress, that's the line still coming in plus everything collected
+    /// from the event's earlier lines
+    pub max_buffered: Option<usize>,
+    /// What to do when any of the limits is exceeded
+    pub on_exceeded: OnLimitExceeded,
+}
+
+impl Limits {
+    /// No limits, erroring out if any are set later
+    pub const fn new() -> Self {
+        Self {
+            max_line_len: None,
+            max_event_data: None,
+            max_buffered: None,
+            on_exceeded: OnLimitExceeded::Error,
+        }
+    }
+}
+
+/// What an event stream does when one of its [`Limits`] is exceeded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnLimitExceeded {
+    /// Yield [`EventStreamError::LimitExceeded`][crate::errors::EventStreamError::LimitExceeded] and end the stream
+    #[default]
+    Error,
+    /// Throw away the event in progress and carry on from the next blank line
+    SkipEvent,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct EventBuilder {
     event: Str,
@@ -19,6 +146,8 @@ pub(crate) struct EventBuilder {
     data_buffer: EventBuilderDataBuffer,
     retry: Option<Duration>,
     is_complete: bool,
+    // set when an event went over a limit, every line is ignored until the blank line that ends it
+    skipping: bool,
 }
 
 // this is an optimisation over using just a StrMut buffer. like 99% of the time we are just gonna have a single data line so we should just take that as the buffer's value and never add the linefeed at all
@@ -65,6 +194,14 @@ impl EventBuilderDataBuffer {
     fn is_empty(&self) -> bool {
         matches!(self, EventBuilderDataBuffer::Uninit)
     }
+
+    fn len(&self) -> usize {
+        match self {
+            EventBuilderDataBuffer::Uninit => 0,
+            EventBuilderDataBuffer::Immutable(str) => str.len(),
+            EventBuilderDataBuffer::Mutable(str_mut) => str_mut.len(),
+        }
+    }
 }
 
 impl Default for EventBuilder {
@@ -75,12 +212,20 @@ impl Default for EventBuilder {
             data_buffer: EventBuilderDataBuffer::default(),
             retry: None,
             is_complete: false,
+            skipping: false,
         }
     }
 }
 
 impl EventBuilder {
     pub(crate) fn add(&mut self, line: ValidatedEventLine) {
+        if self.skipping {
+            if let ValidatedEventLine::Empty = line {
+                self.skipping = false;
+            }
+            return;
+        }
+
         match line {
             ValidatedEventLine::Empty => self.is_complete = true,
             ValidatedEventLine::Field {
@@ -118,7 +263,7 @@ impl EventBuilder {
                 }
             }
             // Comments are ignored, fields with no name are ignored, events with no value do nothing so might as well include them here
-            ValidatedEventLine::Comment
+            ValidatedEventLine::Comment(_)
             | ValidatedEventLine::Field {
                 field_name: FieldName::Ignored,
                 ..
@@ -130,6 +275,25 @@ impl EventBuilder {
         }
     }
 
+    /// Throw away the event in progress and ignore lines up to and including the next blank line
+    pub(crate) fn skip_event(&mut self) {
+        self.event = EMPTY_STR;
+        self.data_buffer = EventBuilderDataBuffer::Uninit;
+        self.retry = None;
+        self.is_complete = false;
+        self.skipping = true;
+    }
+
+    /// Bytes held for the event in progress
+    fn buffered_len(&self) -> usize {
+        self.event.len() + self.data_buffer.len()
+    }
+
+    /// Seed the id carried by events that don't set their own
+    pub(crate) fn set_id(&mut self, id: Str) {
+        self.id = id;
+    }
+
     // Comment taken from https://github.com/jpopesculian/eventsource-stream/blob/main/src/event_stream.rs
     /// From the HTML spec
     ///
@@ -214,44 +378,151 @@ pub(crate) const fn starts_with_bom(buf: &[u8]) -> Option<bool> {
     }
 }
 
-fn parse_event<E>(
-    buffer: &mut BytesMut,
+/// Errors out or starts skipping the event in progress, depending on `limits`
+fn limit_exceeded(
+    exceeded: LimitExceeded,
     builder: &mut EventBuilder,
-) -> Result<Option<Event>, EventStreamError<E>> {
-    if buffer.is_empty() {
+    limits: &Limits,
+) -> Result<(), DecodeError> {
+    match limits.on_exceeded {
+        OnLimitExceeded::Error => Err(DecodeError::LimitExceeded(exceeded)),
+        OnLimitExceeded::SkipEvent => {
+            builder.skip_event();
+            Ok(())
+        }
+    }
+}
+
+/// Checks the limits against a buffer that's been parsed as far as it can go, so all that's left is the start of a line
+fn check_partial_line(
+    buffer: &mut impl Buf,
+    builder: &mut EventBuilder,
+    limits: &Limits,
+) -> Result<(), DecodeError> {
+    let len = buffer.remaining();
+    let exceeded = if limits.max_line_len.is_some_and(|max| len > max) {
+        LimitExceeded::LineLength
+    } else if limits
+        .max_buffered
+        .is_some_and(|max| len + builder.buffered_len() > max)
+    {
+        LimitExceeded::Buffered
+    } else {
+        return Ok(());
+    };
+
+    limit_exceeded(exceeded, builder, limits)?;
+    // keep the tail of the line so whatever's left of it still reads as a line for the builder to ignore. a trailing CR
+    // keeps the byte before it too, otherwise a LF in the next chunk would make it look like a blank line
+    let keep = if buffer.chunk().last() == Some(&CR) {
+        2
+    } else {
+        1
+    };
+    buffer.advance(len - keep.min(len));
+    Ok(())
+}
+
+/// Feeds a single line into the builder, returning an item if the line was a comment the mode wants or if it completed an event
+///
+/// `line_len` is the length of the line including its line ending, for checking against the limits
+#[inline]
+fn parse_event_line<M: CommentMode>(
+    line: RawEventLineOwned,
+    line_len: usize,
+    builder: &mut EventBuilder,
+    last_event_id: &mut Str,
+    options: &ParseOptions,
+) -> Result<Option<M::Item>, DecodeError> {
+    let limits = &options.limits;
+    if limits.max_line_len.is_some_and(|max| line_len > max) {
+        limit_exceeded(LimitExceeded::LineLength, builder, limits)?;
         return Ok(None);
     }
-    loop {
-        let event_line = match parse_line_from_buffer(buffer).map(RawEventLineOwned::validate) {
-            Some(Ok(event_line)) => event_line,
-            Some(Err(e)) => return Err(EventStreamError::Utf8Error(e)),
-            None => return Ok(None),
-        };
 
-        builder.add(event_line);
+    let line = match line {
+        RawEventLineOwned::Comment(_) if builder.skipping => return Ok(None),
+        RawEventLineOwned::Comment(comment) => return Ok(M::comment(comment, options.utf8)?),
+        line => match options.utf8 {
+            Utf8Decoding::Strict => line.validate()?,
+            Utf8Decoding::Lossy => line.validate_lossy(),
+        },
+    };
 
-        // dispatch mutates I don't want to collapse this, for clarity
-        #[allow(clippy::collapsible_if)]
-        if builder.is_complete {
-            if let Some(event) = builder.dispatch() {
-                return Ok(Some(event));
-            }
+    builder.add(line);
+
+    if limits
+        .max_event_data
+        .is_some_and(|max| builder.data_buffer.len() > max)
+    {
+        limit_exceeded(LimitExceeded::EventData, builder, limits)?;
+    } else if limits
+        .max_buffered
+        .is_some_and(|max| builder.buffered_len() > max)
+    {
+        limit_exceeded(LimitExceeded::Buffered, builder, limits)?;
+    }
+
+    // dispatch mutates I don't want to collapse this, for clarity
+    #[allow(clippy::collapsible_if)]
+    if builder.is_complete {
+        if let Some(event) = builder.dispatch() {
+            *last_event_id = event.id.clone();
+            return Ok(Some(event.into()));
         }
     }
+
+    Ok(None)
 }
 
-macro_rules! try_parse_event_buffer {
-    ($this:ident) => {
-        match parse_event($this.buffer, $this.builder) {
-            Ok(Some(event)) => {
-                *$this.last_event_id = event.id.clone();
-                return Poll::Ready(Some(Ok(event)));
-            }
-            Err(e) => return Poll::Ready(Some(Err(e))),
-            _ => {}
+/// Parses lines out of `buffer` until an item comes out or only the start of a line is left
+fn parse_event<M: CommentMode>(
+    buffer: &mut BytesMut,
+    builder: &mut EventBuilder,
+    last_event_id: &mut Str,
+    options: &ParseOptions,
+) -> Result<Option<M::Item>, DecodeError> {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+    let mut len = buffer.len();
+    while let Some(line) = parse_line_from_buffer(buffer) {
+        let line_len = len - buffer.len();
+        len = buffer.len();
+        if let Some(item) = parse_event_line::<M>(line, line_len, builder, last_event_id, options)?
+        {
+            return Ok(Some(item));
         }
-    };
+    }
+    check_partial_line(buffer, builder, &options.limits)?;
+    Ok(None)
+}
+
+/// [`parse_event`] for [`Bytes`], lines are split off without copying
+fn parse_event_bytes<M: CommentMode>(
+    bytes: &mut Bytes,
+    builder: &mut EventBuilder,
+    last_event_id: &mut Str,
+    options: &ParseOptions,
+) -> Result<Option<M::Item>, DecodeError> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    let mut len = bytes.len();
+    while let Some(line) = parse_line_from_bytes(bytes) {
+        let line_len = len - bytes.len();
+        len = bytes.len();
+        if let Some(item) = parse_event_line::<M>(line, line_len, builder, last_event_id, options)?
+        {
+            return Ok(Some(item));
+        }
+    }
+    check_partial_line(bytes, builder, &options.limits)?;
+    Ok(None)
 }
 
+#[cfg(feature = "std")]
+pub mod blocking;
 pub mod bytes;
+pub mod decoder;
 pub mod generic;
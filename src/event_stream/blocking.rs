@@ -0,0 +1,222 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+
+use ::bytes::BytesMut;
+use bytes_utils::Str;
+
+use crate::{
+    errors::EventStreamError,
+    event_stream::{
+        CommentMode, EmitComments, Limits, SkipComments, Utf8Decoding, decoder::Decoder,
+    },
+};
+
+/// Blocking [`Iterator`] that reads [`Event`][crate::event::Event]s out of a [`BufRead`], for when pulling in an async
+/// runtime just to parse a file or a pipe is overkill
+///
+/// ```rust
+/// use sseer::event_stream::blocking::EventReader;
+///
+/// let input = "data: hello\n\ndata: world\n\n".as_bytes();
+/// for event in EventReader::new(input) {
+///     println!("{}", event.unwrap().data);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EventReader<R, M = SkipComments> {
+    reader: R,
+    decoder: Decoder<M>,
+}
+
+impl<R> EventReader<BufReader<R>>
+where
+    R: Read,
+{
+    /// Create a new [`EventReader`] from a plain [`Read`] by wrapping it in a [`BufReader`]
+    pub fn from_read(reader: R) -> Self {
+        Self::new(BufReader::new(reader))
+    }
+}
+
+impl<R> EventReader<R> {
+    /// Create a new [`EventReader`] from a [`BufRead`]
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Yield comment lines as well as events
+    pub fn with_comments(self) -> EventReader<R, EmitComments> {
+        EventReader {
+            reader: self.reader,
+            decoder: self.decoder.with_comments(),
+        }
+    }
+}
+
+impl<R, M> EventReader<R, M> {
+    /// Set the last event id, useful for resumability. Like one received from the stream, events without an `id`
+    /// field carry it on until another id replaces it
+    pub fn set_last_event_id(&mut self, id: impl Into<Str>) {
+        self.decoder.set_last_event_id(id)
+    }
+
+    /// Reference to the last event id given out by this reader
+    pub fn last_event_id(&self) -> &Str {
+        self.decoder.last_event_id()
+    }
+
+    /// Cap how much the reader buffers, see [`Limits`]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.decoder = self.decoder.with_limits(limits);
+        self
+    }
+
+    /// Choose how bytes are decoded into text, see [`Utf8Decoding`]. Defaults to [`Utf8Decoding::Strict`].
+    pub fn with_utf8_decoding(mut self, utf8: Utf8Decoding) -> Self {
+        self.decoder = self.decoder.with_utf8_decoding(utf8);
+        self
+    }
+
+    /// Take the current buffer from the [EventReader], useful if you want to check for leftovers
+    pub fn take_buffer(self) -> BytesMut {
+        // reads are always copied in so there's never a remainder
+        self.decoder.take_buffers().0
+    }
+
+    /// Give back the underlying reader, anything already read into the buffer is lost
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R, M> Iterator for EventReader<R, M>
+where
+    R: BufRead,
+    M: CommentMode,
+{
+    type Item = Result<M::Item, EventStreamError<io::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.decoder.decode() {
+                Ok(Some(item)) => return Some(Ok(item)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            if self.decoder.is_finished() {
+                return None;
+            }
+
+            let new_bytes = match self.reader.fill_buf() {
+                Ok(new_bytes) => new_bytes,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(EventStreamError::Transport(e))),
+            };
+
+            if new_bytes.is_empty() {
+                self.decoder.finish();
+                continue;
+            }
+
+            let len = new_bytes.len();
+            self.decoder.feed_slice(new_bytes);
+            self.reader.consume(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::EMPTY_STR,
+        event::{Event, EventOrComment},
+    };
+
+    fn data(reader: impl BufRead) -> Vec<Str> {
+        EventReader::new(reader)
+            .map(|event| event.unwrap().data)
+            .collect()
+    }
+
+    #[test]
+    fn blocking_reads_events() {
+        let input: &[u8] = b"data: hello\n\nid: 1\nevent: greeting\ndata: wor\ndata: ld\n\n";
+        assert_eq!(
+            EventReader::new(input)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec![
+                Event {
+                    event: Str::from_static("message"),
+                    data: Str::from_static("hello"),
+                    id: EMPTY_STR,
+                    retry: None,
+                },
+                Event {
+                    event: Str::from_static("greeting"),
+                    data: Str::from_static("wor\nld"),
+                    id: Str::from_static("1"),
+                    retry: None,
+                }
+            ]
+        );
+
+        // one byte at a time splits every line, the BOM and every CRLF
+        let input: &[u8] = b"\xEF\xBB\xBFdata: one\r\n\r\ndata: two\r\r";
+        assert_eq!(
+            data(BufReader::with_capacity(1, input)),
+            vec![Str::from_static("one"), Str::from_static("two")]
+        );
+        assert_eq!(
+            data(EventReader::from_read(input).into_inner()),
+            vec![Str::from_static("one"), Str::from_static("two")]
+        );
+
+        // an unfinished event at the end is dropped
+        assert_eq!(
+            data(b"data: one\n\ndata: two".as_slice()),
+            vec![Str::from_static("one")]
+        );
+    }
+
+    #[test]
+    fn blocking_comments() {
+        let input: &[u8] = b": ping\ndata: test\n\n";
+        assert_eq!(
+            EventReader::new(input)
+                .with_comments()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec![
+                EventOrComment::Comment(Str::from_static("ping")),
+                EventOrComment::Event(Event {
+                    event: Str::from_static("message"),
+                    data: Str::from_static("test"),
+                    id: EMPTY_STR,
+                    retry: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn blocking_read_errors() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        let mut reader = EventReader::from_read(Broken);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(EventStreamError::Transport(_)))
+        ));
+    }
+}
@@ -3,55 +3,24 @@ use core::{
     task::{Context, Poll, ready},
 };
 
-use ::bytes::{Buf, BufMut, Bytes, BytesMut};
+use ::bytes::{Bytes, BytesMut};
 use bytes_utils::Str;
 use futures_core::Stream;
 
 use crate::{
-    constants::{BOM, CR, EMPTY_STR, LF},
     errors::EventStreamError,
-    event::Event,
-    event_stream::{EventBuilder, EventStreamState, parse_event, starts_with_bom},
-    parser::{RawEventLineOwned, parse_line_from_bytes},
+    event_stream::{
+        CommentMode, EmitComments, Limits, SkipComments, Utf8Decoding, decoder::Decoder,
+    },
 };
 
-fn parse_event_bytes<E>(
-    bytes: &mut Bytes,
-    builder: &mut EventBuilder,
-) -> Result<Option<Event>, EventStreamError<E>> {
-    if bytes.is_empty() {
-        return Ok(None);
-    }
-    loop {
-        let event_line = match parse_line_from_bytes(bytes).map(RawEventLineOwned::validate) {
-            Some(Ok(event_line)) => event_line,
-            Some(Err(e)) => return Err(EventStreamError::Utf8Error(e)),
-            None => return Ok(None),
-        };
-
-        builder.add(event_line);
-
-        // dispatch mutates I don't want to collapse this, for clarity
-        #[allow(clippy::collapsible_if)]
-        if builder.is_complete {
-            if let Some(event) = builder.dispatch() {
-                return Ok(Some(event));
-            }
-        }
-    }
-}
-
 pin_project_lite::pin_project! {
     /// Like [`EventStream`][super::generic::EventStream] but specialised for streams of [`Bytes`].
     #[derive(Debug)]
-    pub struct EventStreamBytes<S> {
+    pub struct EventStreamBytes<S, M = SkipComments> {
         #[pin]
         stream: S,
-        buffer: BytesMut,
-        remainder: Bytes,
-        builder: EventBuilder,
-        state: EventStreamState,
-        last_event_id: Str,
+        decoder: Decoder<M>,
     }
 }
 
@@ -60,141 +29,74 @@ impl<S> EventStreamBytes<S> {
     pub fn new(stream: S) -> Self {
         Self {
             stream,
-            buffer: BytesMut::new(),
-            remainder: Bytes::new(),
-            builder: EventBuilder::default(),
-            state: EventStreamState::NotStarted,
-            last_event_id: EMPTY_STR,
+            decoder: Decoder::new(),
         }
     }
 
-    /// Set the last event id, useful for resumability
+    /// Yield comment lines as well as events
+    pub fn with_comments(self) -> EventStreamBytes<S, EmitComments> {
+        EventStreamBytes {
+            stream: self.stream,
+            decoder: self.decoder.with_comments(),
+        }
+    }
+}
+
+impl<S, M> EventStreamBytes<S, M> {
+    /// Set the last event id, useful for resumability. Like one received from the stream, events without an `id`
+    /// field carry it on until another id replaces it
     pub fn set_last_event_id(&mut self, id: impl Into<Str>) {
-        self.last_event_id = id.into()
+        self.decoder.set_last_event_id(id)
     }
 
     /// Reference to the last event id given out by this stream
     pub fn last_event_id(&self) -> &Str {
-        &self.last_event_id
+        self.decoder.last_event_id()
+    }
+
+    /// Cap how much the stream buffers, see [`Limits`]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.decoder = self.decoder.with_limits(limits);
+        self
+    }
+
+    /// Choose how bytes are decoded into text, see [`Utf8Decoding`]. Defaults to [`Utf8Decoding::Strict`].
+    pub fn with_utf8_decoding(mut self, utf8: Utf8Decoding) -> Self {
+        self.decoder = self.decoder.with_utf8_decoding(utf8);
+        self
     }
 
     /// Takes the buffer and the remainder
     pub fn take_buffers(self) -> (BytesMut, Bytes) {
-        (self.buffer, self.remainder)
+        self.decoder.take_buffers()
     }
 }
 
-macro_rules! try_parse_remainder {
-    ($this:ident) => {
-        if !$this.remainder.is_empty() {
-            match parse_event_bytes::<E>($this.remainder, $this.builder) {
-                Ok(Some(event)) => {
-                    *$this.last_event_id = event.id.clone();
-                    return Poll::Ready(Some(Ok(event)));
-                }
-                Ok(None) => {
-                    // incomplete event left over must concat with future data
-                    if !$this.remainder.is_empty() {
-                        $this.buffer.extend_from_slice($this.remainder);
-                        *$this.remainder = Bytes::new();
-                    }
-                }
-                Err(e) => return Poll::Ready(Some(Err(e))),
-            }
-        }
-    };
-}
-
-impl<S, E> Stream for EventStreamBytes<S>
+impl<S, E, M> Stream for EventStreamBytes<S, M>
 where
     S: Stream<Item = Result<Bytes, E>>,
+    M: CommentMode,
 {
-    type Item = Result<Event, EventStreamError<E>>;
+    type Item = Result<M::Item, EventStreamError<E>>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
 
-        try_parse_remainder!(this);
-        try_parse_event_buffer!(this);
-
-        if this.state.is_terminated() {
-            return Poll::Ready(None);
-        }
-
         loop {
-            let new_bytes = match ready!(this.stream.as_mut().poll_next(cx)) {
-                Some(Ok(o)) => o,
-                Some(Err(e)) => return Poll::Ready(Some(Err(EventStreamError::Transport(e)))),
-                None => {
-                    *this.state = EventStreamState::Terminated;
-
-                    if !this.remainder.is_empty() {
-                        this.buffer.extend_from_slice(this.remainder);
-                        *this.remainder = Bytes::new();
-                    }
-
-                    if this
-                        .buffer
-                        .last()
-                        .map(|&last| last == CR)
-                        .unwrap_or_default()
-                    {
-                        this.buffer.put_u8(LF);
-                    }
-
-                    try_parse_event_buffer!(this);
-                    return Poll::Ready(None);
-                }
-            };
-
-            if new_bytes.is_empty() {
-                continue;
+            match this.decoder.decode() {
+                Ok(Some(item)) => return Poll::Ready(Some(Ok(item))),
+                Ok(None) => {}
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
             }
 
-            if this.buffer.is_empty() && this.remainder.is_empty() {
-                if this.state.is_not_started() {
-                    match starts_with_bom(&new_bytes) {
-                        Some(true) => {
-                            *this.state = EventStreamState::Started;
-                            let mut b = new_bytes;
-                            b.advance(BOM.len());
-                            *this.remainder = b;
-                        }
-                        Some(false) => {
-                            *this.state = EventStreamState::Started;
-                            *this.remainder = new_bytes;
-                        }
-                        None => {
-                            // potential split BOM
-                            this.buffer.extend_from_slice(&new_bytes);
-                            continue;
-                        }
-                    }
-                } else {
-                    *this.remainder = new_bytes;
-                }
-
-                try_parse_remainder!(this);
-            } else {
-                if !this.remainder.is_empty() {
-                    this.buffer.extend_from_slice(this.remainder);
-                    *this.remainder = Bytes::new();
-                }
-
-                this.buffer.extend_from_slice(&new_bytes);
-
-                if this.state.is_not_started() {
-                    match starts_with_bom(this.buffer) {
-                        Some(true) => {
-                            *this.state = EventStreamState::Started;
-                            this.buffer.advance(BOM.len());
-                        }
-                        Some(false) => *this.state = EventStreamState::Started,
-                        None => continue,
-                    }
-                }
+            if this.decoder.is_finished() {
+                return Poll::Ready(None);
+            }
 
-                try_parse_event_buffer!(this);
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(new_bytes)) => this.decoder.feed(new_bytes),
+                Some(Err(e)) => return Poll::Ready(Some(Err(EventStreamError::Transport(e)))),
+                None => this.decoder.finish(),
             }
         }
     }
@@ -204,7 +106,7 @@ where
 #[cfg(feature = "std")]
 mod tests {
     use super::*;
-    use ::bytes::Bytes;
+    use crate::{constants::EMPTY_STR, event::Event};
     use futures::prelude::*;
 
     #[tokio::test]
@@ -655,4 +557,197 @@ data: test
             ]
         );
     }
+
+    #[tokio::test]
+    async fn bytes_comments() {
+        use crate::event::EventOrComment;
+
+        assert_eq!(
+            EventStreamBytes::new(futures::stream::iter(vec![
+                Ok::<_, ()>(Bytes::from_static(b": ping\n:\n:no space\ndata: te")),
+                Ok::<_, ()>(Bytes::from_static(b"st\n:  two spaces\n\n"))
+            ]))
+            .with_comments()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap(),
+            vec![
+                EventOrComment::Comment(Str::from_static("ping")),
+                EventOrComment::Comment(EMPTY_STR),
+                EventOrComment::Comment(Str::from_static("no space")),
+                EventOrComment::Comment(Str::from_static(" two spaces")),
+                EventOrComment::Event(Event {
+                    event: Str::from_static("message"),
+                    data: Str::from_static("test"),
+                    id: EMPTY_STR,
+                    retry: None,
+                }),
+            ]
+        );
+
+        // comments are never validated unless asked for
+        assert_eq!(
+            EventStreamBytes::new(futures::stream::iter(vec![Ok::<_, ()>(
+                Bytes::from_static(b": \xFF\ndata: test\n\n")
+            )]))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap(),
+            vec![Event {
+                event: Str::from_static("message"),
+                data: Str::from_static("test"),
+                id: EMPTY_STR,
+                retry: None,
+            }]
+        );
+    }
+
+    async fn collect_data(
+        chunks: &[&'static [u8]],
+        limits: Limits,
+    ) -> Vec<Result<Str, EventStreamError<()>>> {
+        EventStreamBytes::new(futures::stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok::<_, ()>(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        ))
+        .with_limits(limits)
+        .map_ok(|event| event.data)
+        .collect()
+        .await
+    }
+
+    #[tokio::test]
+    async fn bytes_limits() {
+        use crate::{errors::LimitExceeded, event_stream::OnLimitExceeded};
+
+        let error = Limits {
+            max_line_len: Some(16),
+            max_event_data: Some(8),
+            max_buffered: Some(16),
+            ..Limits::new()
+        };
+        let skip = Limits {
+            on_exceeded: OnLimitExceeded::SkipEvent,
+            ..error
+        };
+
+        // a line that never ends ends the stream
+        assert_eq!(
+            collect_data(&[b"data: ok\n\n", b"data: aaaaaaaaa", b"aaaaaaa"], error).await,
+            vec![
+                Ok(Str::from_static("ok")),
+                Err(EventStreamError::LimitExceeded(LimitExceeded::LineLength))
+            ]
+        );
+        assert_eq!(
+            collect_data(&[b"data: aaaaaaaaaaaaaaaaa\n\ndata: ok\n\n"], error).await,
+            vec![Err(EventStreamError::LimitExceeded(
+                LimitExceeded::LineLength
+            ))]
+        );
+        assert_eq!(
+            collect_data(&[b"data: 12345\ndata: 6789\n\ndata: ok\n\n"], error).await,
+            vec![Err(EventStreamError::LimitExceeded(
+                LimitExceeded::EventData
+            ))]
+        );
+        assert_eq!(
+            collect_data(&[b"event: abcdefgh\n", b"data: 12345"], error).await,
+            vec![Err(EventStreamError::LimitExceeded(
+                LimitExceeded::Buffered
+            ))]
+        );
+
+        // or only loses the event it's in, a CRLF split over chunks ends the long line just once
+        assert_eq!(
+            collect_data(
+                &[
+                    b"data: ok\n\n",
+                    b"data: aaaaaaaaa",
+                    b"aaaaaaaa\r",
+                    b"\ndata: more\n\n",
+                    b"data: fine\n\n"
+                ],
+                skip
+            )
+            .await,
+            vec![Ok(Str::from_static("ok")), Ok(Str::from_static("fine"))]
+        );
+        assert_eq!(
+            collect_data(&[b"data: aaaaaaaaaaaaaaaaa\n\ndata: ok\n\n"], skip).await,
+            vec![Ok(Str::from_static("ok"))]
+        );
+        assert_eq!(
+            collect_data(&[b"data: 12345\ndata: 6789\n\ndata: ok\n\n"], skip).await,
+            vec![Ok(Str::from_static("ok"))]
+        );
+        assert_eq!(
+            collect_data(
+                &[b"event: abcdefgh\n", b"data: 12345", b"\n\ndata: ok\n\n"],
+                skip
+            )
+            .await,
+            vec![Ok(Str::from_static("ok"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn bytes_lossy_utf8() {
+        use crate::event::EventOrComment;
+
+        let chunks = || {
+            futures::stream::iter(vec![
+                Ok::<_, ()>(Bytes::from_static(b"data: a\xFFb\n")),
+                Ok::<_, ()>(Bytes::from_static(b"data: \xE2\x82")),
+                Ok::<_, ()>(Bytes::from_static(b"\n: \xFF\n\n")),
+            ])
+        };
+
+        assert_eq!(
+            EventStreamBytes::new(chunks())
+                .with_utf8_decoding(Utf8Decoding::Lossy)
+                .with_comments()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap(),
+            vec![
+                EventOrComment::Comment(Str::from_static("\u{FFFD}")),
+                EventOrComment::Event(Event {
+                    event: Str::from_static("message"),
+                    data: Str::from_static("a\u{FFFD}b\n\u{FFFD}"),
+                    id: EMPTY_STR,
+                    retry: None,
+                }),
+            ]
+        );
+
+        // strict mode errors on each bad line but keeps going
+        let results = EventStreamBytes::new(chunks()).collect::<Vec<_>>().await;
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(EventStreamError::Utf8Error(_))));
+        assert!(matches!(results[1], Err(EventStreamError::Utf8Error(_))));
+    }
+
+    #[tokio::test]
+    async fn bytes_seeded_last_event_id() {
+        let mut stream = EventStreamBytes::new(futures::stream::iter(vec![Ok::<_, ()>(
+            Bytes::from_static(b"data: a\n\nid: 2\ndata: b\n\ndata: c\n\n"),
+        )]));
+        stream.set_last_event_id("seed");
+        let ids = stream
+            .map_ok(|event| event.id)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            ids,
+            vec![
+                Str::from_static("seed"),
+                Str::from_static("2"),
+                Str::from_static("2")
+            ]
+        );
+    }
 }
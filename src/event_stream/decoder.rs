@@ -0,0 +1,309 @@
+//! Sans-IO core of every event stream, bytes are pushed in and events are pulled out
+
+use core::marker::PhantomData;
+
+use ::bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes_utils::Str;
+
+use crate::{
+    constants::{BOM, CR, EMPTY_STR, LF},
+    errors::DecodeError,
+    event_stream::{
+        CommentMode, EmitComments, EventBuilder, EventStreamState, Limits, ParseOptions,
+        SkipComments, Utf8Decoding, parse_event, parse_event_bytes, starts_with_bom,
+    },
+};
+
+/// Incremental SSE decoder that doesn't do any IO, for callback based or embedded event loops where there's no
+/// [`Stream`][futures_core::Stream] to wrap
+///
+/// Bytes go in with [`Decoder::feed`] and events come out of [`Decoder::decode`] until it returns [None], meaning
+/// more bytes are needed. Once the input has ended call [`Decoder::finish`] and drain it one last time.
+///
+/// ```rust
+/// use bytes::Bytes;
+/// use sseer::Decoder;
+///
+/// let mut decoder = Decoder::new();
+/// decoder.feed(Bytes::from_static(b"data: hello\n\ndata: wor"));
+/// assert_eq!(decoder.decode().unwrap().unwrap().data, "hello");
+/// assert!(decoder.decode().unwrap().is_none());
+///
+/// decoder.feed(Bytes::from_static(b"ld\n\n"));
+/// decoder.finish();
+/// assert_eq!(decoder.decode().unwrap().unwrap().data, "world");
+/// assert!(decoder.decode().unwrap().is_none());
+/// ```
+#[derive(Debug)]
+pub struct Decoder<M = SkipComments> {
+    buffer: BytesMut,
+    // chunk fed in while nothing was buffered, parsed straight out of the `Bytes` so lines don't have to be copied
+    remainder: Bytes,
+    builder: EventBuilder,
+    state: EventStreamState,
+    last_event_id: Str,
+    options: ParseOptions,
+    mode: PhantomData<fn() -> M>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Create a new [`Decoder`]
+    pub fn new() -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            remainder: Bytes::new(),
+            builder: EventBuilder::default(),
+            state: EventStreamState::NotStarted,
+            last_event_id: EMPTY_STR,
+            options: ParseOptions::new(),
+            mode: PhantomData,
+        }
+    }
+
+    /// Yield comment lines as well as events
+    pub fn with_comments(self) -> Decoder<EmitComments> {
+        Decoder {
+            buffer: self.buffer,
+            remainder: self.remainder,
+            builder: self.builder,
+            state: self.state,
+            last_event_id: self.last_event_id,
+            options: self.options,
+            mode: PhantomData,
+        }
+    }
+}
+
+impl<M> Decoder<M> {
+    /// Set the last event id, useful for resumability. Events without an `id` field carry it on.
+    pub fn set_last_event_id(&mut self, id: impl Into<Str>) {
+        self.last_event_id = id.into();
+        self.builder.set_id(self.last_event_id.clone());
+    }
+
+    /// Reference to the last event id given out by this decoder
+    pub fn last_event_id(&self) -> &Str {
+        &self.last_event_id
+    }
+
+    /// Cap how much the decoder buffers, see [`Limits`]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Choose how bytes are decoded into text, see [`Utf8Decoding`]. Defaults to [`Utf8Decoding::Strict`].
+    pub fn with_utf8_decoding(mut self, utf8: Utf8Decoding) -> Self {
+        self.options.utf8 = utf8;
+        self
+    }
+
+    /// Push the next chunk of input, when nothing is buffered the chunk is parsed in place without being copied
+    ///
+    /// Ignored once the decoder [is finished][Decoder::is_finished].
+    pub fn feed(&mut self, bytes: Bytes) {
+        if bytes.is_empty() || self.state.is_terminated() {
+            return;
+        }
+
+        if !self.buffer.is_empty() || !self.remainder.is_empty() {
+            self.feed_slice(&bytes);
+            return;
+        }
+
+        if self.state.is_not_started() {
+            match starts_with_bom(&bytes) {
+                Some(true) => {
+                    self.state = EventStreamState::Started;
+                    let mut bytes = bytes;
+                    bytes.advance(BOM.len());
+                    self.remainder = bytes;
+                }
+                Some(false) => {
+                    self.state = EventStreamState::Started;
+                    self.remainder = bytes;
+                }
+                // potential split BOM
+                None => self.buffer.extend_from_slice(&bytes),
+            }
+        } else {
+            self.remainder = bytes;
+        }
+    }
+
+    /// Push the next chunk of input by copying it into the buffer
+    ///
+    /// Ignored once the decoder [is finished][Decoder::is_finished].
+    pub fn feed_slice(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() || self.state.is_terminated() {
+            return;
+        }
+
+        self.read_into(|buffer| buffer.extend_from_slice(bytes));
+    }
+
+    /// Lets a reader write straight into the end of the buffer rather than handing over chunks
+    pub(crate) fn read_into<T>(&mut self, read: impl FnOnce(&mut BytesMut) -> T) -> T {
+        self.flush_remainder();
+        let out = read(&mut self.buffer);
+
+        if self.state.is_not_started() {
+            match starts_with_bom(&self.buffer) {
+                Some(true) => {
+                    self.state = EventStreamState::Started;
+                    self.buffer.advance(BOM.len());
+                }
+                Some(false) => self.state = EventStreamState::Started,
+                None => {}
+            }
+        }
+        out
+    }
+
+    /// Mark the end of the input, a trailing CR is treated as a line ending and an event without the blank line that
+    /// would end it is thrown away. Events that were already complete can still be drained with [`Decoder::decode`].
+    pub fn finish(&mut self) {
+        if self.state.is_terminated() {
+            return;
+        }
+        self.state = EventStreamState::Terminated;
+
+        self.flush_remainder();
+        if self.buffer.last() == Some(&CR) {
+            self.buffer.put_u8(LF);
+        }
+    }
+
+    /// Whether [`Decoder::finish`] has been called or a [limit][Limits] ended the input, once [`Decoder::decode`]
+    /// returns [None] nothing more will come out
+    pub fn is_finished(&self) -> bool {
+        self.state.is_terminated()
+    }
+
+    /// Takes the buffer and the remainder, useful if you want to check for leftovers
+    pub fn take_buffers(self) -> (BytesMut, Bytes) {
+        (self.buffer, self.remainder)
+    }
+
+    fn flush_remainder(&mut self) {
+        if !self.remainder.is_empty() {
+            self.buffer.extend_from_slice(&self.remainder);
+            self.remainder = Bytes::new();
+        }
+    }
+
+    // a decoder that errored out on a limit can't pick back up from the middle of a line so it ends there
+    fn terminate_on_limit(&mut self, err: DecodeError) -> DecodeError {
+        if let DecodeError::LimitExceeded(_) = err {
+            self.state = EventStreamState::Terminated;
+            self.buffer.clear();
+            self.remainder = Bytes::new();
+        }
+        err
+    }
+}
+
+impl<M> Decoder<M>
+where
+    M: CommentMode,
+{
+    /// Take the next item out of what's been fed in so far, [None] means more input is needed
+    ///
+    /// A line with invalid UTF-8 errors but the decoder carries on from the next line, a limit set to
+    /// [error out][crate::event_stream::OnLimitExceeded::Error] finishes the decoder.
+    pub fn decode(&mut self) -> Result<Option<M::Item>, DecodeError> {
+        // wait to see if the input starts with a BOM, unless there's no more input coming
+        if self.state.is_not_started() {
+            return Ok(None);
+        }
+
+        if !self.remainder.is_empty() {
+            match parse_event_bytes::<M>(
+                &mut self.remainder,
+                &mut self.builder,
+                &mut self.last_event_id,
+                &self.options,
+            ) {
+                Ok(Some(item)) => return Ok(Some(item)),
+                // incomplete event left over must concat with future data
+                Ok(None) => self.flush_remainder(),
+                Err(e) => return Err(self.terminate_on_limit(e)),
+            }
+        }
+
+        match parse_event::<M>(
+            &mut self.buffer,
+            &mut self.builder,
+            &mut self.last_event_id,
+            &self.options,
+        ) {
+            Ok(item) => Ok(item),
+            Err(e) => Err(self.terminate_on_limit(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::LimitExceeded;
+
+    fn drain(decoder: &mut Decoder) -> [Option<Str>; 3] {
+        [(); 3].map(|_| decoder.decode().unwrap().map(|event| event.data))
+    }
+
+    #[test]
+    fn decoder_feed_and_finish() {
+        let mut decoder = Decoder::new();
+        decoder.feed(Bytes::from_static(b"\xEF"));
+        decoder.feed_slice(b"\xBB");
+        assert_eq!(decoder.decode(), Ok(None));
+        decoder.feed(Bytes::from_static(b"\xBFdata: one\r"));
+        assert_eq!(decoder.decode(), Ok(None));
+        decoder.feed(Bytes::from_static(b"\n\r\ndata: two\n\ndata: thr"));
+        decoder.feed_slice(b"ee\r");
+        assert_eq!(
+            drain(&mut decoder),
+            [
+                Some(Str::from_static("one")),
+                Some(Str::from_static("two")),
+                None
+            ]
+        );
+        assert!(!decoder.is_finished());
+
+        decoder.feed(Bytes::from_static(b"\r"));
+        decoder.finish();
+        decoder.feed(Bytes::from_static(b"data: ignored\n\n"));
+        assert!(decoder.is_finished());
+        assert_eq!(
+            drain(&mut decoder),
+            [Some(Str::from_static("three")), None, None]
+        );
+    }
+
+    #[test]
+    fn decoder_limit_finishes() {
+        let mut decoder = Decoder::new().with_limits(Limits {
+            max_line_len: Some(8),
+            ..Limits::new()
+        });
+        decoder.feed(Bytes::from_static(b"data: a\n\ndata: abcdef"));
+        assert_eq!(
+            decoder.decode().unwrap().map(|event| event.data),
+            Some(Str::from_static("a"))
+        );
+        assert_eq!(
+            decoder.decode(),
+            Err(DecodeError::LimitExceeded(LimitExceeded::LineLength))
+        );
+        assert!(decoder.is_finished());
+        assert_eq!(decoder.decode(), Ok(None));
+    }
+}
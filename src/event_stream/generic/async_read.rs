@@ -0,0 +1,290 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use std::io;
+
+use ::bytes::BytesMut;
+use futures_core::Stream;
+
+use crate::{
+    errors::EventStreamError,
+    event_stream::{CommentMode, generic::EventStream},
+};
+
+/// How much room is made in the buffer before each read
+const READ_SIZE: usize = 8 * 1024;
+
+macro_rules! poll_reader {
+    ($self:ident, $cx:ident, $poll_read:ident) => {{
+        let this = $self.project();
+        let mut stream = this.stream;
+
+        loop {
+            match this.decoder.decode() {
+                Ok(Some(item)) => return Poll::Ready(Some(Ok(item))),
+                Ok(None) => {}
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+
+            if this.decoder.is_finished() {
+                return Poll::Ready(None);
+            }
+
+            let read = this
+                .decoder
+                .read_into(|buffer| $poll_read(stream.as_mut(), $cx, buffer));
+            match ready!(read) {
+                Ok(0) => this.decoder.finish(),
+                Ok(_) => {}
+                Err(e) => return Poll::Ready(Some(Err(EventStreamError::Transport(e)))),
+            }
+        }
+    }};
+}
+
+#[cfg(feature = "tokio")]
+pin_project_lite::pin_project! {
+    /// Source of an [`EventStream`] that reads from a [`tokio::io::AsyncRead`], made by [`EventStream::from_async_read`]
+    #[derive(Debug)]
+    pub struct TokioAsyncRead<R> {
+        #[pin]
+        reader: R,
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R> EventStream<TokioAsyncRead<R>> {
+    /// Create a new [`EventStream`] that reads from a [`tokio::io::AsyncRead`] straight into its own buffer, without the
+    /// extra chunk a [`Stream`] of bytes would need
+    pub fn from_async_read(reader: R) -> Self {
+        Self::new(TokioAsyncRead { reader })
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn poll_tokio_read<R>(
+    stream: Pin<&mut TokioAsyncRead<R>>,
+    cx: &mut Context<'_>,
+    buffer: &mut BytesMut,
+) -> Poll<io::Result<usize>>
+where
+    R: tokio::io::AsyncRead,
+{
+    let reader = stream.project().reader;
+    buffer.reserve(READ_SIZE);
+    let dst = buffer.spare_capacity_mut();
+    let ptr = dst.as_ptr();
+    let mut read_buf = tokio::io::ReadBuf::uninit(dst);
+    ready!(reader.poll_read(cx, &mut read_buf))?;
+
+    // the reader could have swapped in a different buffer
+    assert_eq!(ptr.cast(), read_buf.filled().as_ptr());
+    let n = read_buf.filled().len();
+    // Safety: the reader filled in the first `n` bytes of the spare capacity
+    unsafe { buffer.set_len(buffer.len() + n) };
+    Poll::Ready(Ok(n))
+}
+
+#[cfg(feature = "tokio")]
+impl<R, M> Stream for EventStream<TokioAsyncRead<R>, M>
+where
+    R: tokio::io::AsyncRead,
+    M: CommentMode,
+{
+    type Item = Result<M::Item, EventStreamError<io::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        poll_reader!(self, cx, poll_tokio_read)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+pin_project_lite::pin_project! {
+    /// Source of an [`EventStream`] that reads from a [`futures_io::AsyncRead`], made by
+    /// [`EventStream::from_futures_async_read`]
+    #[derive(Debug)]
+    pub struct FuturesAsyncRead<R> {
+        #[pin]
+        reader: R,
+        initialized: Initialized,
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R> EventStream<FuturesAsyncRead<R>> {
+    /// Create a new [`EventStream`] that reads from a [`futures_io::AsyncRead`] straight into its own buffer, without
+    /// the extra chunk a [`Stream`] of bytes would need
+    pub fn from_futures_async_read(reader: R) -> Self {
+        Self::new(FuturesAsyncRead {
+            reader,
+            initialized: Initialized::default(),
+        })
+    }
+}
+
+/// How much of the buffer's spare capacity has been zeroed or read into, only trusted while the spare capacity starts
+/// at the same address with the same length as it did after the last read
+#[cfg(feature = "futures-io")]
+#[derive(Debug, Default)]
+struct Initialized {
+    spare_start: usize,
+    spare_len: usize,
+    len: usize,
+}
+
+#[cfg(feature = "futures-io")]
+fn poll_futures_read<R>(
+    stream: Pin<&mut FuturesAsyncRead<R>>,
+    cx: &mut Context<'_>,
+    buffer: &mut BytesMut,
+) -> Poll<io::Result<usize>>
+where
+    R: futures_io::AsyncRead,
+{
+    let this = stream.project();
+    buffer.reserve(READ_SIZE);
+    let spare = buffer.spare_capacity_mut();
+    let (spare_start, spare_len) = (spare.as_ptr() as usize, spare.len());
+
+    // futures-io readers need initialised memory, if anything moved or resized the spare capacity since the last read
+    // it is all zeroed again
+    let initialized = this.initialized;
+    if initialized.spare_start != spare_start || initialized.spare_len != spare_len {
+        initialized.len = 0;
+    }
+    let dst = &mut spare[..READ_SIZE];
+    if initialized.len < READ_SIZE {
+        dst[initialized.len..].fill(core::mem::MaybeUninit::new(0));
+        initialized.len = READ_SIZE;
+    }
+    initialized.spare_start = spare_start;
+    initialized.spare_len = spare_len;
+
+    // Safety: every byte of `dst` was initialised above
+    let dst = unsafe { &mut *(dst as *mut [core::mem::MaybeUninit<u8>] as *mut [u8]) };
+    let n = ready!(this.reader.poll_read(cx, dst))?;
+    assert!(
+        n <= READ_SIZE,
+        "reader claimed to read more than it was given"
+    );
+    // Safety: the first `n` bytes of the spare capacity are initialised and hold what was read
+    unsafe { buffer.set_len(buffer.len() + n) };
+    // what's left of the window is still initialised as long as the buffer isn't touched before the next read
+    *initialized = Initialized {
+        spare_start: spare_start + n,
+        spare_len: spare_len - n,
+        len: initialized.len - n,
+    };
+    Poll::Ready(Ok(n))
+}
+
+#[cfg(feature = "futures-io")]
+impl<R, M> Stream for EventStream<FuturesAsyncRead<R>, M>
+where
+    R: futures_io::AsyncRead,
+    M: CommentMode,
+{
+    type Item = Result<M::Item, EventStreamError<io::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        poll_reader!(self, cx, poll_futures_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes_utils::Str;
+    use futures::prelude::*;
+
+    const INPUT: &[u8] = b"\xEF\xBB\xBFdata: one\r\n\r\n: comment\ndata: two\r\r";
+
+    fn expected() -> Vec<Str> {
+        vec![Str::from_static("one"), Str::from_static("two")]
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_async_read() {
+        use tokio::io::AsyncWriteExt;
+
+        let data = EventStream::from_async_read(INPUT)
+            .map_ok(|event| event.data)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(data, expected());
+
+        // a one byte pipe splits the BOM and every line
+        let (reader, mut writer) = tokio::io::duplex(1);
+        tokio::spawn(async move { writer.write_all(INPUT).await });
+        let data = EventStream::from_async_read(reader)
+            .map_ok(|event| event.data)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(data, expected());
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[tokio::test]
+    async fn futures_async_read() {
+        let data = EventStream::from_futures_async_read(INPUT)
+            .with_comments()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 3);
+
+        let data = EventStream::from_futures_async_read(INPUT)
+            .map_ok(|event| event.data)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(data, expected());
+    }
+
+    /// Hands out one byte per read with a [`Poll::Pending`] before each
+    #[cfg(feature = "futures-io")]
+    struct Trickle {
+        input: &'static [u8],
+        pending: bool,
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl futures_io::AsyncRead for Trickle {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let Some((&byte, rest)) = self.input.split_first() else {
+                return Poll::Ready(Ok(0));
+            };
+            buf[0] = byte;
+            self.input = rest;
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[tokio::test]
+    async fn futures_async_read_trickle() {
+        let reader = Trickle {
+            input: INPUT,
+            pending: false,
+        };
+        let data = EventStream::from_futures_async_read(reader)
+            .map_ok(|event| event.data)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(data, expected());
+    }
+}
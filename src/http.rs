@@ -0,0 +1,107 @@
+//! Helpers for any client built on the [`http`] crate types, shared with [`EventSource`][crate::EventSource]
+
+use http::{HeaderMap, HeaderValue, StatusCode, header::CONTENT_TYPE};
+
+pub use crate::errors::InvalidResponse;
+
+/// MIME type of an SSE stream, for `Accept` and `Content-Type` headers
+pub const EVENT_STREAM_MIME: &str = "text/event-stream";
+
+/// Whether a `Content-Type` value is `text/event-stream`, ignoring case and any parameters such as `charset`
+pub fn is_event_stream(content_type: &HeaderValue) -> bool {
+    let essence = match content_type.as_bytes().iter().position(|&b| b == b';') {
+        Some(end) => &content_type.as_bytes()[..end],
+        None => content_type.as_bytes(),
+    };
+    essence
+        .trim_ascii()
+        .eq_ignore_ascii_case(EVENT_STREAM_MIME.as_bytes())
+}
+
+/// Checks a response before reading it as an event stream, the status has to be 2xx and the `Content-Type` has to be
+/// `text/event-stream` if there is one
+pub fn validate_response(status: StatusCode, headers: &HeaderMap) -> Result<(), InvalidResponse> {
+    if !status.is_success() {
+        return Err(InvalidResponse::StatusCode(status));
+    }
+
+    match headers.get(CONTENT_TYPE) {
+        Some(content_type) if !is_event_stream(content_type) => {
+            Err(InvalidResponse::ContentType(content_type.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Convert any [`http::Response`] into an [`EventStreamBytes`][crate::EventStreamBytes], for clients like hyper that
+/// hand back an [`http_body::Body`] instead of a [`Stream`][futures_core::Stream]
+///
+/// The response isn't checked, use [`validate_response`] first if it matters.
+///
+/// ```rust
+/// use bytes::Bytes;
+/// use futures::StreamExt;
+/// use http_body_util::Full;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let response = http::Response::new(Full::new(Bytes::from_static(b"data: hello\n\n")));
+/// sseer::http::validate_response(response.status(), response.headers()).unwrap();
+///
+/// let mut stream = sseer::http::response_to_stream(response);
+/// assert_eq!(stream.next().await.unwrap().unwrap().data, "hello");
+/// # }
+/// ```
+#[cfg(feature = "http-body")]
+pub fn response_to_stream<B>(
+    response: http::Response<B>,
+) -> crate::EventStreamBytes<http_body_util::BodyDataStream<B>>
+where
+    B: http_body::Body<Data = bytes::Bytes>,
+{
+    crate::EventStreamBytes::new(http_body_util::BodyDataStream::new(response.into_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_stream_content_types() {
+        for content_type in [
+            "text/event-stream",
+            "text/event-stream; charset=utf-8",
+            "Text/Event-Stream",
+            " text/event-stream ;charset=utf-8",
+        ] {
+            assert!(
+                is_event_stream(&HeaderValue::from_static(content_type)),
+                "{content_type}"
+            );
+        }
+        for content_type in ["text/plain", "text/event-streams", "application/json"] {
+            assert!(
+                !is_event_stream(&HeaderValue::from_static(content_type)),
+                "{content_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn validates_responses() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(validate_response(StatusCode::OK, &headers), Ok(()));
+        assert_eq!(
+            validate_response(StatusCode::NOT_FOUND, &headers),
+            Err(InvalidResponse::StatusCode(StatusCode::NOT_FOUND))
+        );
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        assert_eq!(
+            validate_response(StatusCode::OK, &headers),
+            Err(InvalidResponse::ContentType(HeaderValue::from_static(
+                "text/html"
+            )))
+        );
+    }
+}
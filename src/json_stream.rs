@@ -1,5 +1,7 @@
 use crate::event::Event;
+use bytes_utils::Str;
 use core::{
+    cell::Cell,
     error::Error,
     fmt::Display,
     marker::PhantomData,
@@ -9,28 +11,52 @@ use core::{
 use futures_core::Stream;
 use serde::de::DeserializeOwned;
 
+pub mod format;
+pub mod shared;
+pub mod tagged;
+use format::{Format, Json, Tracked, WithPath};
+use shared::Parents;
+use tagged::{EventDeserializer, UnknownEvent};
+
 pin_project_lite::pin_project! {
+    /// Stream adapter that deserialises the data of each event into `T` with the [`Format`] `F`, JSON by default. Wrap
+    /// the format in [`WithPath`] for errors with path information via [serde_path_to_error].
     #[derive(Debug)]
-    pub struct JsonStream<T,S, DeserError = serde_json::Error> {
+    pub struct PayloadStream<T, S, F = Json> {
         #[pin]
         stream_state: JsonStreamState<S>,
-        output_marker: PhantomData<fn() -> (T, DeserError)>,
+        options: JsonOptions,
+        // reused by formats that decode the data before deserialising it
+        scratch: Vec<u8>,
+        output_marker: PhantomData<fn() -> (T, F)>,
     }
 }
 
-pub type DefaultJsonStream<T, S> = JsonStream<T, S, serde_json::Error>;
+/// A [`PayloadStream`] of JSON, the format can still be swapped out
+pub type JsonStream<T, S, F = Json> = PayloadStream<T, S, F>;
+
+/// The sentinel OpenAI compatible APIs send as the data of their last event, see [`PayloadStream::with_sentinel`]
+pub const DONE_SENTINEL: &str = "[DONE]";
+
+/// Which events are handled before deserialising
+#[derive(Debug, Clone, Default)]
+struct JsonOptions {
+    sentinel: Option<Str>,
+    skip_empty: bool,
+    skip_non_json: bool,
+    skip_invalid: bool,
+    tagged: Option<UnknownEvent>,
+}
+
+pub type DefaultJsonStream<T, S> = PayloadStream<T, S, Json>;
 
-pub type PathErrorJsonStream<T, S> =
-    JsonStream<T, S, serde_path_to_error::Error<serde_json::Error>>;
+pub type PathErrorJsonStream<T, S> = PayloadStream<T, S, WithPath<Json>>;
 
-impl<T, S, DeserError> JsonStream<T, S, DeserError> {
+impl<T, S> PayloadStream<T, S, Json> {
     #[must_use]
     /// Creates a new [`JsonStream`] atop `stream` that returns type T or an error with path information via [serde_path_to_error]
     pub fn new_path(stream: S) -> PathErrorJsonStream<T, S> {
-        JsonStream {
-            stream_state: JsonStreamState::Active { stream },
-            output_marker: PhantomData::<fn() -> (T, serde_path_to_error::Error<serde_json::Error>)>,
-        }
+        PayloadStream::new(stream)
     }
 
     #[must_use]
@@ -39,11 +65,65 @@ impl<T, S, DeserError> JsonStream<T, S, DeserError> {
     where
         T: DeserializeOwned,
     {
-        JsonStream {
+        PayloadStream::new(stream)
+    }
+}
+
+impl<T, S, F> PayloadStream<T, S, F> {
+    #[must_use]
+    /// Creates a new [`PayloadStream`] atop `stream` that decodes the data of each event with `F`
+    pub fn new(stream: S) -> Self {
+        PayloadStream {
             stream_state: JsonStreamState::Active { stream },
+            options: JsonOptions::default(),
+            scratch: Vec::new(),
             output_marker: PhantomData,
         }
     }
+
+    /// Return errors with path information via [serde_path_to_error], keeping the options set so far
+    pub fn with_path_errors(self) -> PayloadStream<T, S, WithPath<F>> {
+        PayloadStream {
+            stream_state: self.stream_state,
+            options: self.options,
+            scratch: self.scratch,
+            output_marker: PhantomData,
+        }
+    }
+
+    /// End the stream cleanly at the first event whose data is exactly `sentinel`, such as the [`DONE_SENTINEL`] sent
+    /// by OpenAI compatible APIs, instead of failing to deserialise it
+    pub fn with_sentinel(mut self, sentinel: impl Into<Str>) -> Self {
+        self.options.sentinel = Some(sentinel.into());
+        self
+    }
+
+    /// Skip events with no data instead of erroring
+    pub fn skip_empty(mut self) -> Self {
+        self.options.skip_empty = true;
+        self
+    }
+
+    /// Skip events whose data isn't valid JSON, or whichever [`Format`] the stream decodes, instead of erroring. Data
+    /// that is in the format but doesn't fit `T` is still an error.
+    pub fn skip_non_json(mut self) -> Self {
+        self.options.skip_non_json = true;
+        self
+    }
+
+    /// Skip every event that fails to deserialise instead of yielding a [`JsonStreamError::Deserialize`]. Without
+    /// this the error is yielded and the stream carries on with the next event.
+    pub fn skip_invalid(mut self) -> Self {
+        self.options.skip_invalid = true;
+        self
+    }
+
+    /// Deserialise the whole event instead of only its data, so an enum can be matched on the event name with
+    /// `unknown` deciding what happens to names that don't match, see [`tagged`]
+    pub fn with_event_tag(mut self, unknown: UnknownEvent) -> Self {
+        self.options.tagged = Some(unknown);
+        self
+    }
 }
 
 pin_project_lite::pin_project! {
@@ -62,7 +142,29 @@ pin_project_lite::pin_project! {
 #[derive(Debug)]
 pub enum JsonStreamError<E, E2> {
     Stream(E),
-    Deserialize(E2),
+    /// The event couldn't be deserialised, it's kept so you can see what the server actually sent
+    Deserialize {
+        error: E2,
+        event: Event,
+    },
+}
+
+impl<E, E2> JsonStreamError<E, E2> {
+    /// The event that failed to deserialise, [None] for a [`JsonStreamError::Stream`] error
+    pub fn event(&self) -> Option<&Event> {
+        match self {
+            JsonStreamError::Stream(_) => None,
+            JsonStreamError::Deserialize { event, .. } => Some(event),
+        }
+    }
+
+    /// Take out the event that failed to deserialise, [None] for a [`JsonStreamError::Stream`] error
+    pub fn into_event(self) -> Option<Event> {
+        match self {
+            JsonStreamError::Stream(_) => None,
+            JsonStreamError::Deserialize { event, .. } => Some(event),
+        }
+    }
 }
 
 impl<E, E2> Display for JsonStreamError<E, E2>
@@ -73,7 +175,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             JsonStreamError::Stream(e) => e.fmt(f),
-            JsonStreamError::Deserialize(e2) => e2.fmt(f),
+            JsonStreamError::Deserialize { error, .. } => error.fmt(f),
         }
     }
 }
@@ -85,57 +187,294 @@ where
 {
 }
 
-impl<T, S, E> Stream for JsonStream<T, S, serde_json::Error>
+/// What gets deserialised, just the data or the whole event
+enum Input<'a, F> {
+    Data(&'a str, &'a mut Vec<u8>),
+    Event(EventDeserializer<'a, F>),
+}
+
+/// Shared by both error types, pulls events until one deserialises, errors or ends the stream
+fn poll_json<T, S, E, F, D>(
+    mut stream_state: Pin<&mut JsonStreamState<S>>,
+    options: &JsonOptions,
+    scratch: &mut Vec<u8>,
+    cx: &mut Context<'_>,
+    deserialize: impl Fn(Input<'_, F>) -> Result<T, D>,
+    is_syntax: impl Fn(&D) -> bool,
+) -> Poll<Option<Result<T, JsonStreamError<E, D>>>>
 where
     S: Stream<Item = Result<Event, E>>,
-    T: DeserializeOwned,
 {
-    type Item = Result<T, JsonStreamError<E, serde_json::Error>>;
-
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut this = self.project();
-        let stream = match this.stream_state.as_mut().project() {
+    loop {
+        let stream = match stream_state.as_mut().project() {
             JsonStreamStateProjection::Active { stream } => stream,
             JsonStreamStateProjection::Inactive => return Poll::Ready(None),
         };
 
-        let Some(next) = core::task::ready!(stream.poll_next(cx)) else {
-            this.stream_state.set(JsonStreamState::Inactive);
+        let event = match core::task::ready!(stream.poll_next(cx)) {
+            Some(Ok(event)) => event,
+            Some(Err(e)) => return Poll::Ready(Some(Err(JsonStreamError::Stream(e)))),
+            None => {
+                stream_state.set(JsonStreamState::Inactive);
+                return Poll::Ready(None);
+            }
+        };
+
+        if options
+            .sentinel
+            .as_ref()
+            .is_some_and(|sentinel| event.data == *sentinel)
+        {
+            stream_state.set(JsonStreamState::Inactive);
             return Poll::Ready(None);
+        }
+        if options.skip_empty && event.data.is_empty() {
+            continue;
+        }
+
+        let skipped = Cell::new(false);
+        let input = match &options.tagged {
+            Some(unknown) => {
+                Input::Event(EventDeserializer::new(&event, scratch, unknown, &skipped))
+            }
+            None => Input::Data(&event.data, scratch),
+        };
+        let result = {
+            let _parents = Parents::set(&event);
+            deserialize(input)
         };
-        Poll::Ready(Some(next.map_err(JsonStreamError::Stream).and_then(|o| {
-            serde_json::from_str(&o.data).map_err(JsonStreamError::Deserialize)
-        })))
+        match result {
+            Ok(value) => return Poll::Ready(Some(Ok(value))),
+            Err(_) if skipped.get() => continue,
+            Err(e) if options.skip_non_json && is_syntax(&e) => continue,
+            Err(_) if options.skip_invalid => continue,
+            Err(error) => {
+                return Poll::Ready(Some(Err(JsonStreamError::Deserialize { error, event })));
+            }
+        }
     }
 }
 
-impl<T, S, E> Stream for JsonStream<T, S, serde_path_to_error::Error<serde_json::Error>>
+impl<T, S, E, F> Stream for PayloadStream<T, S, F>
 where
     S: Stream<Item = Result<Event, E>>,
     T: DeserializeOwned,
+    F: Format,
 {
-    type Item = Result<T, JsonStreamError<E, serde_path_to_error::Error<serde_json::Error>>>;
+    type Item = Result<T, JsonStreamError<E, F::Error>>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut this = self.project();
-        let stream = match this.stream_state.as_mut().project() {
-            JsonStreamStateProjection::Active { stream } => stream,
-            JsonStreamStateProjection::Inactive => return Poll::Ready(None),
-        };
+        let this = self.project();
+        poll_json(
+            this.stream_state,
+            this.options,
+            this.scratch,
+            cx,
+            |input: Input<'_, F>| match input {
+                Input::Data(data, scratch) => F::deserialize_seed(data, scratch, PhantomData),
+                Input::Event(deserializer) => T::deserialize(deserializer),
+            },
+            F::is_syntax,
+        )
+    }
+}
 
-        let Some(next) = core::task::ready!(stream.poll_next(cx)) else {
-            this.stream_state.set(JsonStreamState::Inactive);
-            return Poll::Ready(None);
-        };
-        match next {
-            Ok(o) => {
-                let mut deserializer = serde_json::Deserializer::from_str(&o.data);
-                Poll::Ready(Some(
-                    serde_path_to_error::deserialize(&mut deserializer)
-                        .map_err(JsonStreamError::Deserialize),
-                ))
+impl<T, S, E, F> Stream for PayloadStream<T, S, WithPath<F>>
+where
+    S: Stream<Item = Result<Event, E>>,
+    T: DeserializeOwned,
+    F: Format,
+{
+    type Item = Result<T, JsonStreamError<E, serde_path_to_error::Error<F::Error>>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        poll_json(
+            this.stream_state,
+            this.options,
+            this.scratch,
+            cx,
+            |input: Input<'_, F>| match input {
+                Input::Data(data, scratch) => {
+                    let mut track = serde_path_to_error::Track::new();
+                    let seed = Tracked {
+                        seed: PhantomData::<T>,
+                        track: &mut track,
+                    };
+                    F::deserialize_seed(data, scratch, seed)
+                        .map_err(|err| serde_path_to_error::Error::new(track.path(), err))
+                }
+                Input::Event(deserializer) => serde_path_to_error::deserialize(deserializer),
+            },
+            |err| F::is_syntax(err.inner()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{EMPTY_STR, MESSAGE_STR};
+    use futures::{StreamExt, TryStreamExt, stream};
+
+    fn events(data: &[&'static str]) -> impl Stream<Item = Result<Event, ()>> + use<> {
+        let events: Vec<_> = data
+            .iter()
+            .map(|data| {
+                Ok(Event {
+                    event: MESSAGE_STR,
+                    data: Str::from_static(data),
+                    id: EMPTY_STR,
+                    retry: None,
+                })
+            })
+            .collect();
+        stream::iter(events)
+    }
+
+    #[tokio::test]
+    async fn json_sentinel_ends_stream() {
+        let values = JsonStream::<u32, _>::new_default(events(&["1", "2", DONE_SENTINEL, "3"]))
+            .with_sentinel(DONE_SENTINEL)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(values, vec![1, 2]);
+
+        let mut stream = JsonStream::<u32, _>::new_default(events(&["1", DONE_SENTINEL]));
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, JsonStreamError::Deserialize { .. }));
+        assert_eq!(err.into_event().unwrap().data, DONE_SENTINEL);
+    }
+
+    fn tagged(
+        events: &[(&'static str, &'static str)],
+    ) -> impl Stream<Item = Result<Event, ()>> + use<> {
+        let events: Vec<_> = events
+            .iter()
+            .map(|(event, data)| {
+                Ok(Event {
+                    event: Str::from_static(event),
+                    data: Str::from_static(data),
+                    id: EMPTY_STR,
+                    retry: None,
+                })
+            })
+            .collect();
+        stream::iter(events)
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    enum Message {
+        #[serde(rename = "ping")]
+        Ping,
+        #[serde(rename = "count")]
+        Count(u32),
+        #[serde(rename = "update")]
+        Update {
+            name: String,
+        },
+        Unknown {
+            event: String,
+            data: String,
+        },
+    }
+
+    #[tokio::test]
+    async fn json_event_tag() {
+        let input = [
+            ("ping", ""),
+            ("count", "3"),
+            ("mystery", "not json"),
+            ("update", r#"{"name":"a"}"#),
+        ];
+
+        let values = JsonStream::<Message, _>::new_default(tagged(&input))
+            .with_event_tag(UnknownEvent::Skip)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Message::Ping,
+                Message::Count(3),
+                Message::Update {
+                    name: String::from("a")
+                }
+            ]
+        );
+
+        let values = JsonStream::<Message, _>::new_path(tagged(&input))
+            .with_event_tag(UnknownEvent::Fallback("Unknown"))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            values[2],
+            Message::Unknown {
+                event: String::from("mystery"),
+                data: String::from("not json")
             }
-            Err(e) => Poll::Ready(Some(Err(JsonStreamError::Stream(e)))),
+        );
+
+        let values = JsonStream::<Message, _>::new_default(tagged(&input))
+            .with_event_tag(UnknownEvent::Error)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            values[2],
+            Err(JsonStreamError::Deserialize { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn json_adjacently_tagged() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(tag = "event", content = "data", rename_all = "lowercase")]
+        enum Adjacent {
+            Count(u32),
+            Ping,
         }
+
+        let input = [("count", "1"), ("mystery", "{}"), ("count", "2")];
+        let values = JsonStream::<Adjacent, _>::new_default(tagged(&input))
+            .with_event_tag(UnknownEvent::Skip)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(values, vec![Adjacent::Count(1), Adjacent::Count(2)]);
+    }
+
+    #[tokio::test]
+    async fn json_skips_events() {
+        let input = ["", "1", "not json", "\"wrong type\"", "2"];
+
+        let values = JsonStream::<u32, _>::new_default(events(&input))
+            .skip_empty()
+            .skip_non_json()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            values[..],
+            [Ok(1), Err(JsonStreamError::Deserialize { .. }), Ok(2)]
+        ));
+
+        let values = JsonStream::<u32, _>::new_default(events(&input))
+            .skip_invalid()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(values, vec![1, 2]);
+
+        let values = JsonStream::<u32, _>::new_path(events(&input))
+            .skip_non_json()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            values[..],
+            [Ok(1), Err(JsonStreamError::Deserialize { .. }), Ok(2)]
+        ));
     }
 }
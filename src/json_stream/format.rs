@@ -0,0 +1,452 @@
+//! Formats the data of each event can be decoded from, picked by the last type parameter of
+//! [`PayloadStream`][super::PayloadStream]
+//!
+//! [`Json`] is always available, [`Cbor`] (requires `cbor` feature) and [`MessagePack`] (requires `msgpack` feature)
+//! read base64 encoded data since SSE can only carry text, and [`SimdJson`] (requires `simd-json` feature) parses
+//! JSON with [`simd_json`]. Wrapping any of them in [`WithPath`] gives errors with path information via
+//! [serde_path_to_error].
+//!
+//! ```rust
+//! use bytes_utils::Str;
+//! use futures::StreamExt;
+//! use sseer::{
+//!     event::Event,
+//!     json_stream::{JsonStreamError, PayloadStream, format::{Json, WithPath}},
+//! };
+//!
+//! #[derive(Debug, serde::Deserialize)]
+//! struct Message {
+//!     #[allow(dead_code)]
+//!     count: u32,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let events = futures::stream::iter([Ok::<_, ()>(Event {
+//!     event: Str::from_static("message"),
+//!     data: Str::from_static(r#"{"count":"one"}"#),
+//!     id: Str::new(),
+//!     retry: None,
+//! })]);
+//!
+//! let mut messages = PayloadStream::<Message, _, WithPath<Json>>::new(events);
+//! let Some(Err(JsonStreamError::Deserialize { error, .. })) = messages.next().await else {
+//!     panic!("`count` isn't a number");
+//! };
+//! assert_eq!(error.path().to_string(), "count");
+//! # }
+//! ```
+
+use core::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer};
+use serde_json::error::Category;
+
+/// A payload format that event data can be deserialised from
+pub trait Format {
+    /// Error for data that couldn't be deserialised
+    type Error: de::Error;
+
+    /// Deserialise `seed` from `data`, formats that don't need to decode the data first should borrow from it so
+    /// [`SharedStr`][super::shared::SharedStr] doesn't have to copy. Formats that do can decode into `scratch`, which
+    /// the stream reuses between events so it may hold leftovers from the last one. `data` should hold exactly one
+    /// value, anything after it is an error.
+    fn deserialize_seed<'de, S>(
+        data: &'de str,
+        scratch: &'de mut Vec<u8>,
+        seed: S,
+    ) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>;
+
+    /// Whether the data wasn't in this format at all rather than the wrong shape, used by
+    /// [`PayloadStream::skip_non_json`][super::PayloadStream::skip_non_json]
+    fn is_syntax(error: &Self::Error) -> bool {
+        let _ = error;
+        false
+    }
+}
+
+/// Decode data with `F` but return errors with path information via [serde_path_to_error]
+#[derive(Debug)]
+pub struct WithPath<F>(PhantomData<F>);
+
+/// Tracks the path to whatever `seed` fails on
+pub(crate) struct Tracked<'a, S> {
+    pub(crate) seed: S,
+    pub(crate) track: &'a mut serde_path_to_error::Track,
+}
+
+impl<'de, S> DeserializeSeed<'de> for Tracked<'_, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.seed
+            .deserialize(serde_path_to_error::Deserializer::new(
+                deserializer,
+                self.track,
+            ))
+    }
+}
+
+/// JSON via [`serde_json`], strings are borrowed straight from the event
+#[derive(Debug)]
+pub struct Json;
+
+impl Format for Json {
+    type Error = serde_json::Error;
+
+    fn deserialize_seed<'de, S>(
+        data: &'de str,
+        _scratch: &'de mut Vec<u8>,
+        seed: S,
+    ) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let mut deserializer = serde_json::Deserializer::from_str(data);
+        let value = seed.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
+    fn is_syntax(error: &Self::Error) -> bool {
+        matches!(error.classify(), Category::Syntax | Category::Eof)
+    }
+}
+
+/// JSON via [`simd_json`], the data is copied into the scratch buffer since simd-json parses in place so strings
+/// borrow from that rather than the event. Values are deserialised straight from the parsed tape without building a
+/// [`simd_json::OwnedValue`] first, `cargo bench --features simd-json` compares it with [`Json`].
+#[cfg(feature = "simd-json")]
+#[derive(Debug)]
+pub struct SimdJson;
+
+#[cfg(feature = "simd-json")]
+impl Format for SimdJson {
+    type Error = simd_json::Error;
+
+    fn deserialize_seed<'de, S>(
+        data: &'de str,
+        scratch: &'de mut Vec<u8>,
+        seed: S,
+    ) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        scratch.clear();
+        scratch.extend_from_slice(data.as_bytes());
+        seed.deserialize(&mut simd_json::Deserializer::from_slice(scratch)?)
+    }
+
+    fn is_syntax(error: &Self::Error) -> bool {
+        error.is_syntax() || error.is_eof()
+    }
+}
+
+/// Error from a format that reads base64 encoded data
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+#[derive(Debug)]
+pub enum Base64Error<E> {
+    /// The data wasn't valid base64
+    Base64(base64::DecodeError),
+    /// The decoded bytes couldn't be deserialised
+    Decode(E),
+    /// There were bytes left over after the value
+    TrailingBytes,
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+impl<E> core::fmt::Display for Base64Error<E>
+where
+    E: core::fmt::Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Base64Error::Base64(e) => e.fmt(f),
+            Base64Error::Decode(e) => e.fmt(f),
+            Base64Error::TrailingBytes => f.write_str("trailing bytes after the value"),
+        }
+    }
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+impl<E> core::error::Error for Base64Error<E> where E: core::error::Error {}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+impl<E> de::Error for Base64Error<E>
+where
+    E: de::Error,
+{
+    fn custom<T>(msg: T) -> Self
+    where
+        T: core::fmt::Display,
+    {
+        Base64Error::Decode(E::custom(msg))
+    }
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn decode_base64<'de, E>(
+    data: &str,
+    scratch: &'de mut Vec<u8>,
+) -> Result<&'de [u8], Base64Error<E>> {
+    use base64::Engine;
+
+    scratch.clear();
+    base64::engine::general_purpose::STANDARD
+        .decode_vec(data, scratch)
+        .map_err(Base64Error::Base64)?;
+    Ok(scratch)
+}
+
+/// Base64 encoded [CBOR](https://cbor.io) via [`cbor4ii`]
+#[cfg(feature = "cbor")]
+#[derive(Debug)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Format for Cbor {
+    type Error = Base64Error<cbor4ii::serde::DecodeError<cbor4ii::core::error::Never>>;
+
+    fn deserialize_seed<'de, S>(
+        data: &'de str,
+        scratch: &'de mut Vec<u8>,
+        seed: S,
+    ) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        use cbor4ii::core::dec::Read;
+
+        let reader = cbor4ii::core::utils::SliceReader::new(decode_base64(data, scratch)?);
+        let mut deserializer = cbor4ii::serde::Deserializer::new(reader);
+        let value = seed
+            .deserialize(&mut deserializer)
+            .map_err(Base64Error::Decode)?;
+        let mut reader = deserializer.into_inner();
+        let Ok(rest) = reader.fill(1);
+        if !rest.as_ref().is_empty() {
+            return Err(Base64Error::TrailingBytes);
+        }
+        Ok(value)
+    }
+
+    fn is_syntax(error: &Self::Error) -> bool {
+        use cbor4ii::{core::dec::Error, serde::DecodeError};
+
+        matches!(
+            error,
+            Base64Error::Base64(_)
+                | Base64Error::TrailingBytes
+                | Base64Error::Decode(DecodeError::Core(
+                    Error::Read(_) | Error::Eof { .. } | Error::Unsupported { .. }
+                ))
+        )
+    }
+}
+
+/// Base64 encoded [MessagePack](https://msgpack.org) via [`rmp_serde`]
+#[cfg(feature = "msgpack")]
+#[derive(Debug)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Format for MessagePack {
+    type Error = Base64Error<rmp_serde::decode::Error>;
+
+    fn deserialize_seed<'de, S>(
+        data: &'de str,
+        scratch: &'de mut Vec<u8>,
+        seed: S,
+    ) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        use rmp_serde::decode::Error;
+        use serde::Deserialize;
+
+        let mut deserializer =
+            rmp_serde::Deserializer::from_read_ref(decode_base64(data, scratch)?);
+        let value = seed
+            .deserialize(&mut deserializer)
+            .map_err(Base64Error::Decode)?;
+        // the deserializer doesn't hand back what's left, so check there isn't another value to read
+        match de::IgnoredAny::deserialize(&mut deserializer) {
+            Err(Error::InvalidMarkerRead(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Ok(value)
+            }
+            _ => Err(Base64Error::TrailingBytes),
+        }
+    }
+
+    fn is_syntax(error: &Self::Error) -> bool {
+        use rmp_serde::decode::Error;
+
+        matches!(
+            error,
+            Base64Error::Base64(_)
+                | Base64Error::TrailingBytes
+                | Base64Error::Decode(Error::InvalidMarkerRead(_) | Error::InvalidDataRead(_))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::EMPTY_STR,
+        event::Event,
+        json_stream::{JsonStreamError, PayloadStream, tagged::UnknownEvent},
+    };
+    use bytes_utils::Str;
+    use futures::{StreamExt, TryStreamExt, stream};
+    use futures_core::Stream;
+    use serde_json::{Value, json};
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Count {
+        count: u32,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    enum Message {
+        #[serde(rename = "count")]
+        Count(Count),
+        #[serde(rename = "ping")]
+        Ping,
+    }
+
+    fn events(encode: &dyn Fn(Value) -> String) -> impl Stream<Item = Result<Event, ()>> + use<> {
+        let events: Vec<_> = [
+            ("count", encode(json!({ "count": 1 }))),
+            ("count", String::from("{not valid")),
+            ("count", encode(json!({ "count": "two" }))),
+            ("ping", String::new()),
+        ]
+        .into_iter()
+        .map(|(event, data)| {
+            Ok(Event {
+                event: Str::from_static(event),
+                data: Str::from(data),
+                id: EMPTY_STR,
+                retry: None,
+            })
+        })
+        .collect();
+        stream::iter(events)
+    }
+
+    /// Runs the same events through `F`, with `encode` writing each value in the format
+    async fn check_format<F>(encode: impl Fn(Value) -> String)
+    where
+        F: Format,
+    {
+        let values = PayloadStream::<Count, _, F>::new(events(&encode))
+            .skip_non_json()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            values[..],
+            [
+                Ok(Count { count: 1 }),
+                Err(JsonStreamError::Deserialize { .. })
+            ]
+        ));
+
+        let values = PayloadStream::<Count, _, F>::new(events(&encode))
+            .with_path_errors()
+            .skip_non_json()
+            .collect::<Vec<_>>()
+            .await;
+        let Err(JsonStreamError::Deserialize { error, .. }) = &values[1] else {
+            panic!("expected a deserialise error");
+        };
+        assert_eq!(error.path().to_string(), "count");
+
+        let values = PayloadStream::<Message, _, F>::new(events(&encode))
+            .with_event_tag(UnknownEvent::Error)
+            .skip_invalid()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![Message::Count(Count { count: 1 }), Message::Ping]
+        );
+    }
+
+    #[cfg(any(feature = "cbor", feature = "msgpack"))]
+    fn base64(bytes: Vec<u8>) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    #[tokio::test]
+    async fn json_format() {
+        check_format::<Json>(|value| value.to_string()).await;
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[tokio::test]
+    async fn simd_json_format() {
+        check_format::<SimdJson>(|value| value.to_string()).await;
+    }
+
+    #[cfg(feature = "cbor")]
+    #[tokio::test]
+    async fn cbor_format() {
+        check_format::<Cbor>(|value| base64(cbor4ii::serde::to_vec(Vec::new(), &value).unwrap()))
+            .await;
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn msgpack_format() {
+        check_format::<MessagePack>(|value| base64(rmp_serde::to_vec(&value).unwrap())).await;
+    }
+
+    /// Two values back to back should be rejected rather than only reading the first
+    #[cfg(any(feature = "cbor", feature = "msgpack"))]
+    fn check_trailing_bytes<F, E>(encode: impl Fn(Value) -> Vec<u8>)
+    where
+        F: Format<Error = Base64Error<E>>,
+        E: core::fmt::Debug,
+    {
+        let value = encode(json!({ "count": 1 }));
+        let mut scratch = Vec::new();
+        assert_eq!(
+            F::deserialize_seed(&base64(value.clone()), &mut scratch, PhantomData::<Count>)
+                .unwrap(),
+            Count { count: 1 }
+        );
+
+        let error =
+            F::deserialize_seed(&base64(value.repeat(2)), &mut scratch, PhantomData::<Count>)
+                .unwrap_err();
+        assert!(matches!(error, Base64Error::TrailingBytes));
+        assert!(F::is_syntax(&error));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_trailing_bytes() {
+        check_trailing_bytes::<Cbor, _>(|value| {
+            cbor4ii::serde::to_vec(Vec::new(), &value).unwrap()
+        });
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_trailing_bytes() {
+        check_trailing_bytes::<MessagePack, _>(|value| rmp_serde::to_vec(&value).unwrap());
+    }
+}
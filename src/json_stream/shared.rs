@@ -0,0 +1,204 @@
+//! Zero-copy strings for [`JsonStream`][super::JsonStream], a [`SharedStr`] field slices the event it came from
+//! instead of copying into a new `String`
+//!
+//! ```rust
+//! use bytes_utils::Str;
+//! use futures::StreamExt;
+//! use sseer::{
+//!     event::Event,
+//!     json_stream::{JsonStream, shared::SharedStr},
+//! };
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Token {
+//!     text: SharedStr,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let event = Event {
+//!     event: Str::from_static("message"),
+//!     data: Str::from(String::from(r#"{"text":"hello"}"#)),
+//!     id: Str::new(),
+//!     retry: None,
+//! };
+//! let data = event.data.clone();
+//!
+//! let mut tokens = JsonStream::<Token, _>::new_default(futures::stream::iter([Ok::<_, ()>(event)]));
+//! let token = tokens.next().await.unwrap().unwrap();
+//! assert_eq!(*token.text, "hello");
+//! // the text points into the event's data
+//! assert!(data.as_bytes().as_ptr_range().contains(&token.text.as_ptr()));
+//! # }
+//! ```
+
+use core::{
+    cell::RefCell,
+    fmt::{Display, Formatter},
+    ops::Deref,
+};
+
+use bytes_utils::Str;
+use serde::de::{Deserialize, Deserializer, Error, Visitor};
+
+use crate::event::Event;
+
+std::thread_local! {
+    // the event being deserialised on this thread, borrowed strings inside it can be sliced out
+    static PARENTS: RefCell<Option<[Str; 2]>> = const { RefCell::new(None) };
+}
+
+/// Makes the event available to [`SharedStr`] until dropped, then puts back whatever was there before
+pub(crate) struct Parents(Option<[Str; 2]>);
+
+impl Parents {
+    pub(crate) fn set(event: &Event) -> Self {
+        Self(PARENTS.replace(Some([event.data.clone(), event.event.clone()])))
+    }
+}
+
+impl Drop for Parents {
+    fn drop(&mut self) {
+        PARENTS.set(self.0.take());
+    }
+}
+
+/// A [`Str`] that deserialises by slicing the event being read by a [`JsonStream`][super::JsonStream] whenever the
+/// JSON string has no escapes, only copying when it has to
+///
+/// Outside of a [`JsonStream`][super::JsonStream], or with a [format][super::format] that decodes the data into a
+/// new buffer first, it deserialises like a `String`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedStr(pub Str);
+
+impl SharedStr {
+    /// Take the [`Str`] out
+    pub fn into_inner(self) -> Str {
+        self.0
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = Str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for SharedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl From<SharedStr> for Str {
+    fn from(value: SharedStr) -> Self {
+        value.0
+    }
+}
+
+impl From<Str> for SharedStr {
+    fn from(value: Str) -> Self {
+        Self(value)
+    }
+}
+
+/// Slice `s` out of the event being deserialised, if that's where it points
+fn slice_parent(s: &str) -> Option<Str> {
+    let sub = s.as_bytes().as_ptr_range();
+    PARENTS.with_borrow(|parents| {
+        parents.as_ref()?.iter().find_map(|parent| {
+            let range = parent.as_bytes().as_ptr_range();
+            (range.start <= sub.start && sub.end <= range.end).then(|| parent.slice_ref(s))
+        })
+    })
+}
+
+struct SharedStrVisitor;
+
+impl<'de> Visitor<'de> for SharedStrVisitor {
+    type Value = SharedStr;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(SharedStr(
+            slice_parent(v).unwrap_or_else(|| Str::from(String::from(v))),
+        ))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(SharedStr(Str::from(String::from(v))))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(SharedStr(Str::from(v)))
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedStr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(SharedStrVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_stream::{JsonStream, tagged::UnknownEvent};
+    use futures::{TryStreamExt, stream};
+
+    fn points_into(parent: &Str, s: &Str) -> bool {
+        parent.as_bytes().as_ptr_range().contains(&s.as_ptr())
+    }
+
+    #[tokio::test]
+    async fn shared_str_slices_event() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Fields {
+            event: SharedStr,
+            data: Vec<SharedStr>,
+        }
+
+        let event = Event {
+            event: Str::from(String::from("update")),
+            data: Str::from(String::from(r#"["plain", "esc\"aped"]"#)),
+            id: Str::new(),
+            retry: None,
+        };
+        let (name, data) = (event.event.clone(), event.data.clone());
+
+        let fields = JsonStream::<Fields, _>::new_default(stream::iter([Ok::<_, ()>(event)]))
+            .with_event_tag(UnknownEvent::Error)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(*fields.event, "update");
+        assert!(points_into(&name, &fields.event));
+        assert_eq!(*fields.data[0], "plain");
+        assert!(points_into(&data, &fields.data[0]));
+        assert_eq!(*fields.data[1], "esc\"aped");
+        assert!(!points_into(&data, &fields.data[1]));
+
+        // outside of a stream there's nothing to slice
+        let copied: SharedStr = serde_json::from_str(r#""plain""#).unwrap();
+        assert_eq!(*copied, "plain");
+    }
+}
@@ -0,0 +1,341 @@
+//! Deserialising a whole [`Event`] rather than just its data, so the event name can pick an enum variant, see
+//! [`JsonStream::with_event_tag`][super::JsonStream::with_event_tag]
+//!
+//! A plain enum is matched on the event name, each variant is deserialised from the event's data in the stream's
+//! [`Format`] and a unit variant ignores the data:
+//!
+//! ```rust
+//! use bytes_utils::Str;
+//! use futures::TryStreamExt;
+//! use sseer::{
+//!     event::Event,
+//!     json_stream::{JsonStream, tagged::UnknownEvent},
+//! };
+//!
+//! #[derive(Debug, PartialEq, serde::Deserialize)]
+//! enum Message {
+//!     #[serde(rename = "ping")]
+//!     Ping,
+//!     #[serde(rename = "update")]
+//!     Update { count: u32 },
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let events = [("ping", ""), ("typing", "..."), ("update", r#"{"count":1}"#)].map(|(event, data)| {
+//!     Ok::<_, ()>(Event {
+//!         event: Str::from_static(event),
+//!         data: Str::from_static(data),
+//!         id: Str::new(),
+//!         retry: None,
+//!     })
+//! });
+//!
+//! let messages = JsonStream::<Message, _>::new_default(futures::stream::iter(events))
+//!     .with_event_tag(UnknownEvent::Skip)
+//!     .try_collect::<Vec<_>>()
+//!     .await
+//!     .unwrap();
+//! assert_eq!(messages, [Message::Ping, Message::Update { count: 1 }]);
+//! # }
+//! ```
+//!
+//! Anything else sees the event as a `{"event": ..., "data": ...}` map, which is what an adjacently tagged enum
+//! `#[serde(tag = "event", content = "data")]` expects.
+
+use core::{cell::Cell, marker::PhantomData};
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, VariantAccess, Visitor,
+    value::{BorrowedStrDeserializer, MapAccessDeserializer},
+};
+
+use crate::{event::Event, json_stream::format::Format};
+
+/// What to do with an event whose name isn't one of the variants
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UnknownEvent {
+    /// Yield a deserialise error
+    #[default]
+    Error,
+    /// Skip the event, for adjacently tagged enums this skips any event whose name can't be deserialised
+    Skip,
+    /// Deserialise the variant with this name from a `{"event": ..., "data": ...}` map where the data is left as a
+    /// string, capturing events that might not even be in the stream's format. A unit variant ignores the event altogether.
+    ///
+    /// Only plain enums are matched on the event name, use `#[serde(other)]` for adjacently tagged enums.
+    Fallback(&'static str),
+}
+
+/// [`Deserializer`] over an [`Event`], `skipped` is set when an event is [skipped][UnknownEvent::Skip] so the error
+/// can be told apart from a real one
+pub(crate) struct EventDeserializer<'de, F> {
+    event: &'de Event,
+    scratch: &'de mut Vec<u8>,
+    unknown: &'de UnknownEvent,
+    skipped: &'de Cell<bool>,
+    format: PhantomData<fn() -> F>,
+}
+
+impl<'de, F> EventDeserializer<'de, F> {
+    pub(crate) fn new(
+        event: &'de Event,
+        scratch: &'de mut Vec<u8>,
+        unknown: &'de UnknownEvent,
+        skipped: &'de Cell<bool>,
+    ) -> Self {
+        Self {
+            event,
+            scratch,
+            unknown,
+            skipped,
+            format: PhantomData,
+        }
+    }
+
+    fn into_map(self, raw_data: bool) -> EventMap<'de, F> {
+        EventMap {
+            event: self.event,
+            scratch: Some(self.scratch),
+            unknown: self.unknown,
+            skipped: self.skipped,
+            format: PhantomData,
+            raw_data,
+            next: MapField::Event,
+        }
+    }
+}
+
+/// Hands a tuple variant's visitor to the format's deserializer
+struct TupleSeed<V> {
+    len: usize,
+    visitor: V,
+}
+
+impl<'de, V> DeserializeSeed<'de> for TupleSeed<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(self.len, self.visitor)
+    }
+}
+
+/// Hands a struct variant's visitor to the format's deserializer
+struct StructSeed<V> {
+    fields: &'static [&'static str],
+    visitor: V,
+}
+
+impl<'de, V> DeserializeSeed<'de> for StructSeed<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("", self.fields, self.visitor)
+    }
+}
+
+impl<'de, F> Deserializer<'de> for EventDeserializer<'de, F>
+where
+    F: Format,
+{
+    type Error = F::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(self.into_map(false))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let name: &'de str = &self.event.event;
+        if variants.contains(&name) {
+            return visitor.visit_enum(EventEnum {
+                variant: name,
+                content: Content::<F>::Data(&self.event.data, self.scratch),
+            });
+        }
+
+        match self.unknown {
+            UnknownEvent::Error => Err(de::Error::unknown_variant(name, variants)),
+            UnknownEvent::Skip => {
+                self.skipped.set(true);
+                Err(de::Error::custom(format_args!(
+                    "skipped unknown event `{name}`"
+                )))
+            }
+            UnknownEvent::Fallback(variant) => visitor.visit_enum(EventEnum {
+                variant,
+                content: Content::Event(self),
+            }),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MapField {
+    Event,
+    Data,
+    Done,
+}
+
+/// The `{"event": ..., "data": ...}` view of an [`Event`]
+struct EventMap<'de, F> {
+    event: &'de Event,
+    // taken when the data is deserialised
+    scratch: Option<&'de mut Vec<u8>>,
+    unknown: &'de UnknownEvent,
+    skipped: &'de Cell<bool>,
+    format: PhantomData<fn() -> F>,
+    // the fallback keeps the data as a string as it might not be in the format
+    raw_data: bool,
+    next: MapField,
+}
+
+impl<'de, F> MapAccess<'de> for EventMap<'de, F>
+where
+    F: Format,
+{
+    type Error = F::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = match self.next {
+            MapField::Event => "event",
+            MapField::Data => "data",
+            MapField::Done => return Ok(None),
+        };
+        seed.deserialize(BorrowedStrDeserializer::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let event = self.event;
+        match self.next {
+            MapField::Event => {
+                self.next = MapField::Data;
+                let result = seed.deserialize(BorrowedStrDeserializer::new(&event.event));
+                // the only way a plain string can fail is by not being one of the expected names
+                if result.is_err() && !self.raw_data && *self.unknown == UnknownEvent::Skip {
+                    self.skipped.set(true);
+                }
+                result
+            }
+            MapField::Data => {
+                self.next = MapField::Done;
+                match self.scratch.take() {
+                    Some(scratch) if !self.raw_data => {
+                        F::deserialize_seed(&event.data, scratch, seed)
+                    }
+                    _ => seed.deserialize(BorrowedStrDeserializer::new(&event.data)),
+                }
+            }
+            MapField::Done => Err(de::Error::custom("no more values in event")),
+        }
+    }
+}
+
+enum Content<'de, F> {
+    Data(&'de str, &'de mut Vec<u8>),
+    Event(EventDeserializer<'de, F>),
+}
+
+struct EventEnum<'de, F> {
+    variant: &'de str,
+    content: Content<'de, F>,
+}
+
+impl<'de, F> EnumAccess<'de> for EventEnum<'de, F>
+where
+    F: Format,
+{
+    type Error = F::Error;
+    type Variant = Content<'de, F>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(self.variant))?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de, F> VariantAccess<'de> for Content<'de, F>
+where
+    F: Format,
+{
+    type Error = F::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self {
+            Content::Data(data, scratch) => F::deserialize_seed(data, scratch, seed),
+            Content::Event(de) => seed.deserialize(MapAccessDeserializer::new(de.into_map(true))),
+        }
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Content::Data(data, scratch) => {
+                F::deserialize_seed(data, scratch, TupleSeed { len, visitor })
+            }
+            Content::Event(de) => visitor.visit_map(de.into_map(true)),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Content::Data(data, scratch) => {
+                F::deserialize_seed(data, scratch, StructSeed { fields, visitor })
+            }
+            Content::Event(de) => visitor.visit_map(de.into_map(true)),
+        }
+    }
+}
@@ -7,11 +7,33 @@
 //!   `Stream<Item = Result<impl AsRef<[u8]>, E>>` into a stream of parsed [`Event`][event::Event]s.
 //! - [`EventSource`] (requires `reqwest` feature) - a batteries-included HTTP client that
 //!   wraps [`reqwest`] with automatic reconnection, retry policies, and the `Last-Event-ID` header.
+//! - [`event_source::EventSource`] (requires `event-source` feature) - the same reconnection logic on top of any
+//!   HTTP client, implement [`Connector`][event_source::Connector] for hyper, a WASM fetch shim or an in-memory
+//!   transport.
 //! - [`JsonStream`][json_stream::JsonStream] (requires `json` feature) - a stream adapter
-//!   that deserialises each event's `data` field into a typed value via [`serde_json`].
+//!   that deserialises each event's `data` field into a typed value via [`serde_json`], optionally stopping at the
+//!   OpenAI style `[DONE]` sentinel, or maps the event name onto an enum variant with
+//!   [`with_event_tag`][json_stream::PayloadStream::with_event_tag]. [`SharedStr`][json_stream::shared::SharedStr]
+//!   fields slice the event's data instead of copying. It's a [`PayloadStream`][json_stream::PayloadStream] that
+//!   can decode other [formats][json_stream::format] too, such as base64 encoded CBOR or MessagePack.
+//! - [`Decoder`] - the sans-IO core the streams are built on, feed it bytes and pull events out from any event
+//!   loop, no [`Stream`][futures_core::Stream] needed.
+//! - [`EventReader`][event_stream::blocking::EventReader] (requires `std` feature) - the blocking version of
+//!   [`EventStream`], an [`Iterator`] of events read from any [`BufRead`][std::io::BufRead].
 //! - [`Utf8Stream`][utf8_stream::Utf8Stream] - validates and converts a raw byte stream into
 //!   a stream of UTF-8 [`Str`][bytes_utils::Str]s, buffering incomplete multi-byte sequences across
 //!   chunks.
+//! - [`EventEncoder`][encoder::EventEncoder] - the other direction, writes [`Event`][event::Event]s and partial
+//!   [`EventFrame`][encoder::EventFrame]s into a [`BytesMut`][::bytes::BytesMut] for serving SSE.
+//! - [`http::response_to_stream`] (requires `http-body` feature) - the same as [`response_to_stream`] for any
+//!   [`http::Response`][::http::Response] with an [`http_body::Body`], such as one from hyper, with
+//!   [`http::validate_response`] to check it first.
+//! - [`server::sse_response`] (requires `server` feature) - serve SSE from axum or any other [`http`][::http] based
+//!   server, turns a [`Stream`][futures_core::Stream] of [`Event`][event::Event]s into a response that flushes every
+//!   event with optional keep-alive comments. [`KeepAlive`][server::KeepAlive] does the same for any transport that
+//!   takes a stream of [`Bytes`][::bytes::Bytes].
+//! - [`EventCodec`][codec::EventCodec] (requires `tokio-codec` feature) - a [`tokio_util::codec`] decoder and
+//!   encoder for reading and writing SSE over any `AsyncRead` / `AsyncWrite` with `Framed`.
 //! - Low-level parsing via [`parser::parse_line`] and [`parser::parse_line_from_buffer`] for
 //!   custom integrations.
 //!
@@ -32,6 +54,7 @@
 //!         Ok(StreamEvent::Event(evt)) => {
 //!             println!("{}: {}", evt.event, evt.data);
 //!         }
+//!         Ok(StreamEvent::Comment(_)) => {} // only emitted after `EventSource::with_comments`
 //!         Err(e) if e.is_response_err() => break,
 //!         Err(e) => eprintln!("error: {e}"),
 //!     }
@@ -94,24 +117,42 @@
 //! | Feature | Default | Description | no std? |
 //! | --- | --- | --- | --- |
 //! | `serde` | off | Derives [`Serialize`][::serde::Serialize] and [`Deserialize`][::serde::Deserialize] on [`Event`][event::Event] and enables `serde` support in [`bytes-utils`][bytes_utils]. | false |
-//! | `std` | off | Enables standard library support in core dependencies (`bytes`, `memchr`, `futures-core`, etc.). Notably enables runtime SIMD for memchr. Turned on automatically by `reqwest` and `json`. | false |
+//! | `std` | off | Enables standard library support in core dependencies (`bytes`, `memchr`, `futures-core`, etc.). Notably enables runtime SIMD for memchr. Also provides the blocking [`EventReader`][event_stream::blocking::EventReader]. Turned on automatically by `reqwest` and `json`. | false |
 //! | `reqwest` | off | Provides [`EventSource`] for HTTP-based SSE with automatic reconnection and configurable retry policies. | false |
+//! | `event-source` | off | Provides [`event_source::EventSource`], the client agnostic reconnection logic behind [`EventSource`], for any HTTP client that implements [`Connector`][event_source::Connector]. Turned on automatically by `reqwest`. | false |
 //! | `json` | off | Provides [`JsonStream`][json_stream::JsonStream] for deserialising event data into typed values via [`serde_json`] and lets you choose between the default errors or [`serde_path_to_error`] for richer errors. | false |
+//! | `cbor` | off | Provides [`Cbor`][json_stream::format::Cbor] for base64 encoded CBOR event data via `cbor4ii`. Turns on `json`. | false |
+//! | `msgpack` | off | Provides [`MessagePack`][json_stream::format::MessagePack] for base64 encoded MessagePack event data via `rmp-serde`. Turns on `json`. | false |
+//! | `simd-json` | off | Provides [`SimdJson`][json_stream::format::SimdJson] for parsing JSON event data with `simd-json`. Turns on `json`. | false |
+//! | `tokio` | off | Provides [`EventStream::from_async_read`] for reading straight from a `tokio::io::AsyncRead`. | false |
+//! | `futures-io` | off | Provides [`EventStream::from_futures_async_read`] for reading straight from a `futures_io::AsyncRead`. | false |
+//! | `http-body` | off | Provides [`http::response_to_stream`] for any [`http_body::Body`] along with the response checks [`EventSource`] uses. | false |
+//! | `server` | off | Provides [`server::sse_response`], [`server::SseBody`] and the [`server::KeepAlive`] stream for serving SSE from any server built on [`http`][::http] and [`http_body`], such as axum. | false |
+//! | `tokio-codec` | off | Provides [`EventCodec`][codec::EventCodec], a [`tokio_util::codec`] `Decoder` and `Encoder` for SSE. | false |
 //!
 //! Without any features enabled, the crate is fully `no_std` compatible and provides
-//! [`EventStream`], [`Utf8Stream`][utf8_stream::Utf8Stream], the low-level parser,
+//! [`EventStream`], [`Decoder`], [`Utf8Stream`][utf8_stream::Utf8Stream], the low-level parser, the encoder
 //! and retry policy types.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
 pub(crate) mod constants;
+pub mod encoder;
 pub mod errors;
 pub mod event;
+#[cfg(feature = "event-source")]
+pub mod event_source;
 pub mod event_stream;
+#[cfg(any(feature = "http-body", feature = "event-source"))]
+pub mod http;
 pub mod parser;
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
 pub mod retry;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod utf8_stream;
 
 #[cfg(feature = "json")]
@@ -120,7 +161,7 @@ pub mod json_stream;
 #[cfg(feature = "reqwest")]
 pub use reqwest::EventSource;
 
-pub use event_stream::{bytes::EventStreamBytes, generic::EventStream};
+pub use event_stream::{bytes::EventStreamBytes, decoder::Decoder, generic::EventStream};
 
 #[cfg(feature = "reqwest")]
 /// Convert a [`Response`][::reqwest::Response] into a [`Stream`][futures_core::Stream] via a similar mechanism to [::reqwest::Response::bytes_stream]
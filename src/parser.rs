@@ -5,12 +5,13 @@ use core::str::Utf8Error;
 use bytes::{Buf, Bytes, BytesMut};
 use bytes_utils::Str;
 
-use crate::constants::{CR, LF};
+use crate::constants::{CR, LF, REPLACEMENT_CHARACTER};
 
 /// A full line from an SSE stream
 #[derive(Debug, Clone, Copy)]
 pub enum RawEventLine<'a> {
-    Comment, // we choose to ignore this since the OG code never uses it anyways
+    /// A line starting with a colon, holds the text after the colon with a single leading space stripped
+    Comment(&'a [u8]),
     Field {
         field_name: &'a [u8],
         field_value: Option<&'a [u8]>,
@@ -21,7 +22,7 @@ pub enum RawEventLine<'a> {
 /// Full line from an SSE stream, owned version of [RawEventLine]. Note: You probably want to [RawEventLineOwned::validate] these into [ValidatedEventLine]s
 #[derive(Debug, Clone)]
 pub enum RawEventLineOwned {
-    Comment,
+    Comment(Bytes),
     Empty,
     Field {
         field_name: Bytes,
@@ -42,7 +43,7 @@ pub enum FieldName {
 /// Completely parsed SSE event line
 #[derive(Debug, Clone)]
 pub enum ValidatedEventLine {
-    Comment,
+    Comment(Str),
     Empty,
     Field {
         field_name: FieldName,
@@ -50,17 +51,54 @@ pub enum ValidatedEventLine {
     },
 }
 
-fn validate_bytes(val: Bytes) -> Result<Str, Utf8Error> {
+pub(crate) fn validate_bytes(val: Bytes) -> Result<Str, Utf8Error> {
     match str::from_utf8(val.as_ref()) {
         Ok(_) => Ok(unsafe { Str::from_inner_unchecked(val) }),
         Err(e) => Err(e),
     }
 }
 
+/// Like [`validate_bytes`] but swaps invalid sequences for U+FFFD the same way `String::from_utf8_lossy` does, only
+/// copying when there's something to replace
+pub(crate) fn validate_bytes_lossy(val: Bytes) -> Str {
+    if str::from_utf8(val.as_ref()).is_ok() {
+        // Safety: just checked
+        return unsafe { Str::from_inner_unchecked(val) };
+    }
+
+    let mut buf = BytesMut::with_capacity(val.len() + REPLACEMENT_CHARACTER.len());
+    for chunk in val.utf8_chunks() {
+        buf.extend_from_slice(chunk.valid().as_bytes());
+        if !chunk.invalid().is_empty() {
+            buf.extend_from_slice(REPLACEMENT_CHARACTER.as_bytes());
+        }
+    }
+    // Safety: only valid chunks and replacement characters were written
+    unsafe { Str::from_inner_unchecked(buf.freeze()) }
+}
+
 impl RawEventLineOwned {
     pub fn validate(self) -> Result<ValidatedEventLine, core::str::Utf8Error> {
+        self.validate_with(validate_bytes)
+    }
+
+    /// Like [`RawEventLineOwned::validate`] but replaces invalid UTF-8 with U+FFFD instead of failing, as the
+    /// [spec](https://html.spec.whatwg.org/multipage/server-sent-events.html#parsing-an-event-stream) asks for
+    pub fn validate_lossy(self) -> ValidatedEventLine {
+        match self.validate_with(|b| Ok::<_, core::convert::Infallible>(validate_bytes_lossy(b))) {
+            Ok(line) => line,
+            Err(never) => match never {},
+        }
+    }
+
+    fn validate_with<Err>(
+        self,
+        validate: impl Fn(Bytes) -> Result<Str, Err>,
+    ) -> Result<ValidatedEventLine, Err> {
         match self {
-            RawEventLineOwned::Comment => Ok(ValidatedEventLine::Comment),
+            RawEventLineOwned::Comment(comment) => {
+                Ok(ValidatedEventLine::Comment(validate(comment)?))
+            }
             RawEventLineOwned::Empty => Ok(ValidatedEventLine::Empty),
             RawEventLineOwned::Field {
                 field_name,
@@ -75,7 +113,7 @@ impl RawEventLineOwned {
                 };
 
                 let field_value = match field_value {
-                    Some(b) => Some(validate_bytes(b)?),
+                    Some(b) => Some(validate(b)?),
                     None => None,
                 };
 
@@ -120,17 +158,17 @@ fn split_at_next_eol(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
 fn read_line(bytes: &[u8]) -> RawEventLine<'_> {
     match memchr::memchr(b':', bytes) {
         Some(colon_pos) => {
+            let value = &bytes[colon_pos + 1..];
+            // strip single leading space if present
+
+            // ngl i found this syntax out from claude, pattern matching is crazy
+            let value = match value {
+                [b' ', rest @ ..] => rest,
+                _ => value,
+            };
             if colon_pos == 0 {
-                RawEventLine::Comment
+                RawEventLine::Comment(value)
             } else {
-                let value = &bytes[colon_pos + 1..];
-                // strip single leading space if present
-
-                // ngl i found this syntax out from claude, pattern matching is crazy
-                let value = match value {
-                    [b' ', rest @ ..] => rest,
-                    _ => value,
-                };
                 RawEventLine::Field {
                     field_name: &bytes[..colon_pos],
                     field_value: Some(value),
@@ -169,13 +207,18 @@ pub fn parse_line_from_buffer(buffer: &mut BytesMut) -> Option<RawEventLineOwned
     }
 
     match memchr::memchr(b':', &line) {
-        Some(0) => Some(RawEventLineOwned::Comment),
         Some(colon_pos) => {
             let value_start = if line.get(colon_pos + 1) == Some(&b' ') {
                 colon_pos + 2
             } else {
                 colon_pos + 1
             };
+            if colon_pos == 0 {
+                // advancing avoids the refcount bump that slicing would cost
+                let mut comment = line;
+                comment.advance(value_start);
+                return Some(RawEventLineOwned::Comment(comment));
+            }
             Some(RawEventLineOwned::Field {
                 field_name: line.slice(..colon_pos),
                 field_value: Some(line.slice(value_start..)),
@@ -199,13 +242,18 @@ pub fn parse_line_from_bytes(buffer: &mut Bytes) -> Option<RawEventLineOwned> {
     }
 
     match memchr::memchr(b':', &line) {
-        Some(0) => Some(RawEventLineOwned::Comment),
         Some(colon_pos) => {
             let value_start = if line.get(colon_pos + 1) == Some(&b' ') {
                 colon_pos + 2
             } else {
                 colon_pos + 1
             };
+            if colon_pos == 0 {
+                // advancing avoids the refcount bump that slicing would cost
+                let mut comment = line;
+                comment.advance(value_start);
+                return Some(RawEventLineOwned::Comment(comment));
+            }
             Some(RawEventLineOwned::Field {
                 field_name: line.slice(..colon_pos),
                 field_value: Some(line.slice(value_start..)),
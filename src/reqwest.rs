@@ -1,461 +1,755 @@
-use core::{
-    error::Error,
-    fmt::{Display, Formatter},
-    pin::Pin,
-    task::{Context, Poll, ready},
-    time::Duration,
-};
+//! [`EventSource`] over [`reqwest`], the reconnecting [`event_source::EventSource`] with a [`ReqwestConnector`]
 
-use bytes_utils::Str;
 use futures_core::future::BoxFuture;
-use futures_timer::Delay;
-
 use http_body_util::BodyDataStream;
-use pin_project_lite::pin_project;
 use reqwest::{
-    Body, Error as ReqwestError, RequestBuilder, Response, StatusCode,
-    header::{ACCEPT, CONTENT_TYPE, HeaderName, HeaderValue},
+    Body, Client, Error as ReqwestError, Method, Request, RequestBuilder, Response, StatusCode,
+    Url,
+    header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, LOCATION},
 };
 
 use crate::{
-    constants::EMPTY_STR,
-    errors::{CantCloneError, EventStreamError},
-    event::Event,
-    event_stream::bytes::EventStreamBytes,
-    response_to_stream,
-    retry::{DEFAULT_RETRY, ExponentialBackoff, RetryPolicy},
+    errors::CantCloneError,
+    event_source::{self, Connection, Connector},
+    http::EVENT_STREAM_MIME,
+    retry::ExponentialBackoff,
 };
 
-/// Events emitted by [EventSource]
+pub use crate::event_source::StreamEvent;
+
+/// [`event_source::EventSource`] that connects with [`reqwest`]
+pub type EventSource<R = ExponentialBackoff> = event_source::EventSource<ReqwestConnector, R>;
+
+/// What went wrong in an [`EventSource`], handed to the [`RetryPolicy`][crate::retry::RetryPolicy] to decide whether
+/// to reconnect
+pub type EventSourceErrorKind = event_source::EventSourceErrorKind<ReqwestError, Response>;
+
+/// Error from an [`EventSource`]
+pub type EventSourceError = event_source::EventSourceError<ReqwestError, Response>;
+
+/// Most redirects followed for one connection, the same limit reqwest has by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Headers that aren't sent on to another origin, the same ones reqwest strips when it follows a redirect
+const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "cookie",
+    "cookie2",
+    "proxy-authorization",
+    "www-authenticate",
+];
+
+/// Where an unbroken chain of permanent redirects ended up, handed from the connection future to
+/// [`ReqwestConnector::on_open`]
 #[derive(Debug, Clone)]
-pub enum StreamEvent {
-    /// A new connection has been opened
-    Open,
-    Event(Event),
+struct PermanentRedirect(Url);
+
+/// [`Connector`] that sends a cloned [`RequestBuilder`] for every connection
+///
+/// Redirects that reach the connector are followed by it, and after a connection opens at the end of only permanent
+/// (301 or 308) redirects later connections go straight there. Credential headers such as `Authorization` and
+/// `Cookie` are dropped whenever a redirect changes origin. reqwest clients follow redirects themselves by default
+/// without saying which kind they were, build the client with [`reqwest::redirect::Policy::none`] to have the
+/// connector follow them instead, otherwise every connection starts over from the original URL.
+#[derive(Debug)]
+pub struct ReqwestConnector {
+    builder: RequestBuilder,
+    final_url: Option<Url>,
+    permanent_url: Option<Url>,
 }
 
-impl From<Event> for StreamEvent {
-    fn from(event: Event) -> Self {
-        StreamEvent::Event(event)
+impl ReqwestConnector {
+    /// Create a new [`ReqwestConnector`], the request has to be cloneable so it can be sent again when reconnecting
+    pub fn new(request: RequestBuilder) -> Result<Self, CantCloneError> {
+        let builder = request.header(ACCEPT, HeaderValue::from_static(EVENT_STREAM_MIME));
+        if builder.try_clone().is_none() {
+            return Err(CantCloneError);
+        }
+        Ok(Self {
+            builder,
+            final_url: None,
+            permanent_url: None,
+        })
     }
-}
 
-pin_project! {
-    #[project = EventSourceProjection]
-    #[derive(Debug)]
-    pub struct EventSource<R> {
-        builder: RequestBuilder,
-        #[pin]
-        connection_state: ConnectionState,
-        last_event_id: Str,
-        retry_policy: R
+    /// The URL of the last successful connection after following any redirects, [None] until the first connection is opened
+    pub fn final_url(&self) -> Option<&Url> {
+        self.final_url.as_ref()
     }
-}
 
-pin_project! {
-    #[project = ConnectionStateProjection]
-    enum ConnectionState {
-        Connecting {
-            #[pin]
-            future: BoxFuture<'static, Result<Response, ReqwestError>>,
-            retry_state: Option<(usize, Duration)>,
-        },
-        Retrying {
-            #[pin]
-            delay: Delay,
-            attempt_number: usize,
-            delay_duration: Duration,
-        },
-        Open {
-            #[pin]
-            stream: EventStreamBytes<BodyDataStream<Body>>,
-            retry_state: Option<(usize, Duration)>,
-        },
-        Closed,
+    /// Where connections start from after being permanently redirected, [None] if they start from the original URL
+    pub fn permanent_url(&self) -> Option<&Url> {
+        self.permanent_url.as_ref()
     }
 }
 
-impl std::fmt::Debug for ConnectionState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Connecting { retry_state, .. } => f
-                .debug_struct("Connecting")
-                .field("future", &"future")
-                .field("retry_state", retry_state)
-                .finish(),
-            Self::Retrying {
-                delay,
-                attempt_number,
-                delay_duration,
-            } => f
-                .debug_struct("Retrying")
-                .field("delay", delay)
-                .field("attempt_number", attempt_number)
-                .field("delay_duration", delay_duration)
-                .finish(),
-            Self::Open {
-                stream,
-                retry_state,
-            } => f
-                .debug_struct("Open")
-                .field("stream", stream)
-                .field("retry_state", retry_state)
-                .finish(),
-            Self::Closed => write!(f, "Closed"),
+/// Point `request` at `url`, leaving its credentials behind if that's another origin
+fn redirect_to(request: &mut Request, url: Url) {
+    if url.origin() != request.url().origin() {
+        for name in SENSITIVE_HEADERS {
+            request.headers_mut().remove(name);
         }
     }
+    *request.url_mut() = url;
 }
 
-impl<'pin, R> EventSourceProjection<'pin, R> {
-    fn initiate_connection(
-        &mut self,
-        retry_state: Option<(usize, Duration)>,
-    ) -> Result<(), EventSourceErrorKind> {
-        let req = self.builder.try_clone().unwrap().header(
-            HeaderName::from_static("last-event-id"),
-            HeaderValue::from_str(self.last_event_id).map_err(|_| {
-                EventSourceErrorKind::InvalidLastEventId(self.last_event_id.clone())
-            })?,
-        );
-        let res_future = Box::pin(req.send());
-        *self.connection_state = ConnectionState::Connecting {
-            future: res_future,
-            retry_state,
+/// Send `request`, following any redirects the client hands back rather than following itself
+async fn send_following_redirects(
+    client: Client,
+    mut request: Request,
+) -> Result<Response, ReqwestError> {
+    let mut all_permanent = true;
+    let mut permanent_url = None;
+    let mut redirects = 0;
+    loop {
+        let next = request.try_clone();
+        let mut response = client.execute(request).await?;
+        let status = response.status();
+        let location = match status {
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok()),
+            _ => None,
+        };
+        let (Some(mut next), Some(location), true) = (next, location, redirects < MAX_REDIRECTS)
+        else {
+            if let Some(url) = permanent_url {
+                response.extensions_mut().insert(PermanentRedirect(url));
+            }
+            return Ok(response);
         };
-        Ok(())
-    }
 
-    fn handle_successful_response(
-        &mut self,
-        response: Response,
-        retry_state: Option<(usize, Duration)>,
-    ) -> Result<(), EventSourceErrorKind> {
-        let status = response.status();
-        if !status.is_success() {
-            return Err(EventSourceErrorKind::InvalidStatusCode {
-                status,
-                response: Box::new(response),
-            });
+        all_permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        if all_permanent {
+            permanent_url = Some(location.clone());
         }
-
-        if let Some(content_type) = response.headers().get(CONTENT_TYPE)
-            && !content_type.as_bytes().starts_with(b"text/event-stream")
-        {
-            return Err(EventSourceErrorKind::InvalidContentType {
-                status,
-                content_type: content_type.clone(),
-                response: Box::new(response),
-            });
+        // like browsers a 303 or a POST redirected by 301 or 302 is sent on as a GET without its body
+        let method = next.method();
+        let becomes_get = (status == StatusCode::SEE_OTHER && method != Method::HEAD)
+            || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                && method == Method::POST);
+        if becomes_get {
+            *next.method_mut() = Method::GET;
+            *next.body_mut() = None;
+            next.headers_mut().remove(CONTENT_TYPE);
+            next.headers_mut().remove(CONTENT_LENGTH);
         }
+        redirect_to(&mut next, location);
+        request = next;
+        redirects += 1;
+    }
+}
 
-        let stream = response_to_stream(response);
-        *self.connection_state = ConnectionState::Open {
-            stream,
-            retry_state,
+impl Connector for ReqwestConnector {
+    type Error = ReqwestError;
+    type Connection = Response;
+    type Future = BoxFuture<'static, Result<Response, ReqwestError>>;
+
+    fn connect(&mut self, last_event_id: Option<&HeaderValue>) -> Self::Future {
+        // checked when the connector was made
+        let mut req = self.builder.try_clone().unwrap();
+        if let Some(last_event_id) = last_event_id {
+            req = req.header(
+                HeaderName::from_static("last-event-id"),
+                last_event_id.clone(),
+            );
+        }
+        let (client, request) = req.build_split();
+        let mut request = match request {
+            Ok(request) => request,
+            Err(e) => return Box::pin(core::future::ready(Err(e))),
         };
-        Ok(())
+        // skip the permanent redirects that were already followed
+        if let Some(permanent_url) = self.permanent_url.as_ref() {
+            redirect_to(&mut request, permanent_url.clone());
+        }
+        Box::pin(send_following_redirects(client, request))
     }
 
-    fn start_retry(&mut self, attempt_number: usize, delay_duration: Duration) {
-        self.connection_state.set(ConnectionState::Retrying {
-            delay: Delay::new(delay_duration),
-            attempt_number,
-            delay_duration,
-        })
+    fn on_open(&mut self, connection: &Response) {
+        self.final_url = Some(connection.url().clone());
+        if let Some(PermanentRedirect(url)) = connection.extensions().get() {
+            self.permanent_url = Some(url.clone());
+        }
     }
+}
 
-    fn handle_error(&mut self, err: &EventSourceErrorKind, last_retry: Option<(usize, Duration)>)
-    where
-        R: RetryPolicy<EventSourceErrorKind>,
-    {
-        if let Some(retry_delay) = self.retry_policy.retry(err, last_retry) {
-            let retry_num = last_retry.map(|retry| retry.0).unwrap_or(1);
-            self.start_retry(retry_num, retry_delay);
-        } else {
-            self.connection_state.set(ConnectionState::Closed);
-        }
+impl Connection for Response {
+    type Error = ReqwestError;
+    type Body = BodyDataStream<Body>;
+
+    fn status(&self) -> StatusCode {
+        self.status()
     }
 
-    fn handle_event(&mut self, event: &Event)
-    where
-        R: RetryPolicy<EventSourceErrorKind>,
-    {
-        *self.last_event_id = event.id.clone();
-        if let Some(duration) = event.retry {
-            self.retry_policy.set_reconnection_time(duration)
-        }
+    fn headers(&self) -> &HeaderMap {
+        self.headers()
+    }
+
+    fn into_body(self) -> Self::Body {
+        BodyDataStream::new(Body::from(self))
     }
 }
 
 impl<R> EventSource<R> {
+    /// Create a new [`EventSource`] that reconnects according to `retry_policy`, which sees every error as described
+    /// in [`event_source::EventSource::from_connector_with_retry`]
     pub fn new_with_retry(
         request: RequestBuilder,
         retry_policy: R,
     ) -> Result<Self, CantCloneError> {
-        let request = request.header(ACCEPT, HeaderValue::from_static("text/event-stream"));
-        let req_fut = Box::pin(request.try_clone().ok_or(CantCloneError)?.send());
-
-        Ok(EventSource {
-            builder: request,
-            connection_state: ConnectionState::Connecting {
-                future: req_fut,
-                retry_state: None,
-            },
-            last_event_id: EMPTY_STR,
+        Ok(Self::from_connector_with_retry(
+            ReqwestConnector::new(request)?,
             retry_policy,
-        })
+        ))
+    }
+
+    /// The URL of the last successful connection after following any redirects, [None] until the first connection is opened
+    pub fn final_url(&self) -> Option<&Url> {
+        self.connector().final_url()
     }
 }
 
 impl EventSource<ExponentialBackoff> {
+    /// Create a new [`EventSource`] with the default [`ExponentialBackoff`]. Like a browser it closes on responses that
+    /// retrying won't fix, such as 401, 403 and 404, see [`event_source::EventSource::from_connector`].
     pub fn new(request: RequestBuilder) -> Result<EventSource<ExponentialBackoff>, CantCloneError> {
-        let request = request.header(ACCEPT, HeaderValue::from_static("text/event-stream"));
-        let req_fut = Box::pin(request.try_clone().ok_or(CantCloneError)?.send());
-
-        Ok(EventSource {
-            builder: request,
-            connection_state: ConnectionState::Connecting {
-                future: req_fut,
-                retry_state: None,
-            },
-            last_event_id: EMPTY_STR,
-            retry_policy: DEFAULT_RETRY,
-        })
+        Ok(Self::from_connector(ReqwestConnector::new(request)?))
     }
 }
 
-#[derive(Debug)]
-enum EventSourceErrorKind {
-    /// The last event ID contains non-visible ascii characters, unusable in a [`HeaderValue`]
-    InvalidLastEventId(Str),
-    /// Reqwest has had an error when getting a response
-    Transport(ReqwestError),
-    /// The underlying stream has ran into an error
-    Stream(EventStreamError<ReqwestError>),
-    /// Received a [non 2xx][reqwest::StatusCode::is_success] response
-    InvalidStatusCode {
-        status: StatusCode,
-        response: Box<Response>, // boxed because this was a big error
-    },
-    /// Received a 2XX status, but the [Content-Type][CONTENT_TYPE] was not "text/event-stream"
-    InvalidContentType {
-        status: StatusCode,
-        content_type: HeaderValue,
-        response: Box<Response>,
-    },
-    /// The underlying stream has ran to completion
-    StreamEnded, // not sure how i feel about this being an error tbh, change me?
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event_source::parse_retry_after,
+        retry::{Constant, MAX_DELAY, RetryPolicy, RetryReset},
+    };
+    use bytes_utils::Str;
+    use core::time::Duration;
+    use futures::StreamExt;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    /// A canned response for [`serve`], `hang` keeps the connection open after writing instead of closing it
+    struct MockResponse {
+        raw: &'static [u8],
+        hang: bool,
+    }
 
-impl Display for EventSourceErrorKind {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        match self {
-            EventSourceErrorKind::InvalidLastEventId(s) => s.fmt(f),
-            EventSourceErrorKind::Transport(err) => err.fmt(f),
-            EventSourceErrorKind::Stream(err) => err.fmt(f),
-            EventSourceErrorKind::InvalidStatusCode { status, .. } => write!(
-                f,
-                "got non 2XX status code {status}: '{canonical}'",
-                canonical = status.canonical_reason().unwrap_or("no canonical reason")
-            ),
-            EventSourceErrorKind::InvalidContentType {
-                status,
-                content_type,
-                ..
-            } => write!(
-                f,
-                "got invalid content-type '{content_type}' on status '{status}' request",
-                content_type = content_type
-                    .to_str()
-                    .unwrap_or("unable to read content-type as str")
-            ),
-            EventSourceErrorKind::StreamEnded => "stream ended".fmt(f),
+    const fn respond(raw: &'static [u8]) -> MockResponse {
+        MockResponse { raw, hang: false }
+    }
+
+    const fn respond_then_hang(raw: &'static [u8]) -> MockResponse {
+        MockResponse { raw, hang: true }
+    }
+
+    /// Closes the connection without writing a response
+    const fn hang_up() -> MockResponse {
+        MockResponse {
+            raw: b"",
+            hang: false,
         }
     }
-}
 
-impl Error for EventSourceErrorKind {}
+    /// Serves one canned response per connection in order, sending back the raw request head of every connection
+    async fn serve(responses: Vec<MockResponse>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        (url, serve_on(listener, responses))
+    }
 
-#[derive(Debug)]
-pub struct EventSourceError {
-    retry_state: Option<(usize, Duration)>,
-    kind: EventSourceErrorKind,
-}
+    fn serve_on(
+        listener: TcpListener,
+        responses: Vec<MockResponse>,
+    ) -> mpsc::UnboundedReceiver<String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                let _ = tx.send(String::from_utf8(head).unwrap());
+
+                socket.write_all(response.raw).await.unwrap();
+                if response.hang {
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        drop(socket);
+                    });
+                }
+            }
+        });
+
+        rx
+    }
 
-impl Display for EventSourceError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let retry_state = self
-            .retry_state
-            .as_ref()
-            .map(|(attempts, duration)| format!("attempts: '{attempts}' duration: {duration:?}"))
-            .unwrap_or(String::from("no last retry"));
-        write!(
-            f,
-            "Error '{kind}' with last retry: {retry_state}",
-            kind = self.kind
+    fn event_source(url: &str) -> EventSource<Constant> {
+        EventSource::new_with_retry(
+            reqwest::Client::new().get(url),
+            Constant::new(Duration::from_millis(10), Some(5)),
         )
+        .unwrap()
     }
-}
 
-impl std::error::Error for EventSourceError {}
+    fn event_data(item: Option<Result<StreamEvent, EventSourceError>>) -> Str {
+        match item {
+            Some(Ok(StreamEvent::Event(event))) => event.data,
+            other => panic!("expected an event, got {other:?}"),
+        }
+    }
 
-impl EventSourceError {
-    fn new(
-        kind: impl Into<EventSourceErrorKind>,
-        retry_state: impl Into<Option<(usize, Duration)>>,
-    ) -> Self {
-        Self {
-            retry_state: retry_state.into(),
-            kind: kind.into(),
+    fn is_open(item: &Option<Result<StreamEvent, EventSourceError>>) -> bool {
+        matches!(item, Some(Ok(StreamEvent::Open)))
+    }
+
+    #[tokio::test]
+    async fn idle_timeout_reconnects() {
+        let (url, mut requests) = serve(vec![
+            respond_then_hang(
+                b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\nid: 1\ndata: first\n\n: ping\n",
+            ),
+            respond(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: second\n\n"),
+        ])
+        .await;
+
+        let mut source = event_source(&url)
+            .with_idle_timeout(Duration::from_millis(200))
+            .with_comments();
+
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "first");
+        assert!(matches!(
+            source.next().await,
+            Some(Ok(StreamEvent::Comment(comment))) if comment == "ping"
+        ));
+        match source.next().await {
+            Some(Err(err)) => assert!(err.is_idle_timeout(), "{err}"),
+            other => panic!("expected an idle timeout, got {other:?}"),
+        }
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "second");
+
+        requests.recv().await.unwrap();
+        let reconnect = requests.recv().await.unwrap().to_lowercase();
+        assert!(reconnect.contains("last-event-id: 1\r\n"), "{reconnect}");
+    }
+
+    /// Retries 5xx and network errors, gives up on anything else
+    struct ServerErrorsOnly;
+
+    impl RetryPolicy<EventSourceErrorKind> for ServerErrorsOnly {
+        fn retry(
+            &self,
+            err: &EventSourceErrorKind,
+            _last_retry: Option<(usize, Duration)>,
+        ) -> Option<Duration> {
+            let server_error = err
+                .status_code()
+                .is_some_and(|status| status.is_server_error());
+            (server_error || err.is_transport()).then_some(Duration::from_millis(10))
+        }
+
+        fn set_reconnection_time(&mut self, _duration: Duration) {}
+    }
+
+    #[tokio::test]
+    async fn custom_policy_inspects_error_kind() {
+        let (url, _requests) = serve(vec![
+            respond(
+                b"HTTP/1.1 503 Service Unavailable\r\nretry-after: 1\r\ncontent-length: 0\r\n\r\n",
+            ),
+            respond(b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n"),
+        ])
+        .await;
+
+        let mut unavailable =
+            EventSource::new_with_retry(reqwest::Client::new().get(&url), ServerErrorsOnly)
+                .unwrap();
+        match unavailable.next().await {
+            Some(Err(err)) => {
+                assert_eq!(err.status_code(), Some(StatusCode::SERVICE_UNAVAILABLE));
+                assert_eq!(
+                    err.retry_after_header(),
+                    Some(&HeaderValue::from_static("1"))
+                );
+                assert!(ServerErrorsOnly.retry(err.kind(), None).is_some());
+            }
+            other => panic!("expected a 503, got {other:?}"),
+        }
+
+        let mut unauthorized =
+            EventSource::new_with_retry(reqwest::Client::new().get(&url), ServerErrorsOnly)
+                .unwrap();
+        match unauthorized.next().await {
+            Some(Err(err)) => {
+                assert!(matches!(
+                    err.kind(),
+                    EventSourceErrorKind::InvalidStatusCode { status, .. } if *status == StatusCode::UNAUTHORIZED
+                ));
+                assert!(!err.is_transport());
+                assert!(ServerErrorsOnly.retry(err.kind(), None).is_none());
+            }
+            other => panic!("expected a 401, got {other:?}"),
         }
     }
 
-    /// Was this error caused by [Response::status] being a non 2XX
-    pub fn is_status_code(&self) -> bool {
-        matches!(self.kind, EventSourceErrorKind::InvalidStatusCode { .. })
+    fn expect_err(item: Option<Result<StreamEvent, EventSourceError>>) -> EventSourceError {
+        match item {
+            Some(Err(err)) => err,
+            other => panic!("expected an error, got {other:?}"),
+        }
     }
 
-    /// Was this error caused by [Content-Type][CONTENT_TYPE] not being "text/event-stream"
-    pub fn is_content_type(&self) -> bool {
-        matches!(self.kind, EventSourceErrorKind::InvalidContentType { .. })
+    const OK_RESPONSE: &[u8] =
+        b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: hello\n\n";
+
+    const REDIRECT_RESPONSE: &[u8] = b"HTTP/1.1 307 Temporary Redirect\r\nlocation: /moved\r\nconnection: close\r\ncontent-length: 0\r\n\r\n";
+
+    #[tokio::test]
+    async fn retries_bad_responses() {
+        let (url, _requests) = serve(vec![
+            hang_up(),
+            respond(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n"),
+            respond(b"HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: 0\r\n\r\n"),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source = event_source(&url);
+        assert!(expect_err(source.next().await).is_transport());
+        assert!(expect_err(source.next().await).is_status_code());
+        assert!(expect_err(source.next().await).is_content_type());
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "hello");
     }
 
-    /// Was the error caused by an invalid [`Response`]
-    pub fn is_response_err(&self) -> bool {
-        matches!(
-            self.kind,
-            EventSourceErrorKind::InvalidContentType { .. }
-                | EventSourceErrorKind::InvalidStatusCode { .. }
+    #[tokio::test]
+    async fn reconnects_after_connection_refused() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let mut source = EventSource::new_with_retry(
+            reqwest::Client::new().get(format!("http://{addr}/")),
+            Constant::new(Duration::from_millis(200), Some(3)),
         )
+        .unwrap();
+
+        assert!(expect_err(source.next().await).is_transport());
+
+        let _requests = serve_on(
+            TcpListener::bind(addr).await.unwrap(),
+            vec![respond(OK_RESPONSE)],
+        );
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "hello");
     }
 
-    /// Get the status code that caused this error, if it was caused by an invalid [`Response`]
-    pub fn status_code(&self) -> Option<StatusCode> {
-        match &self.kind {
-            EventSourceErrorKind::InvalidStatusCode { status, .. }
-            | EventSourceErrorKind::InvalidContentType { status, .. } => Some(*status),
-            _ => None,
+    #[tokio::test]
+    async fn policy_can_give_up_on_connect() {
+        let (url, _requests) = serve(vec![
+            respond(b"HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\n\r\n"),
+            respond(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n"),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source =
+            EventSource::new_with_retry(reqwest::Client::new().get(&url), ServerErrorsOnly)
+                .unwrap();
+
+        assert_eq!(
+            expect_err(source.next().await).status_code(),
+            Some(StatusCode::BAD_GATEWAY)
+        );
+        assert_eq!(
+            expect_err(source.next().await).status_code(),
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert!(source.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn no_content_closes() {
+        let (url, _requests) = serve(vec![respond(b"HTTP/1.1 204 No Content\r\n\r\n")]).await;
+
+        let mut source = event_source(&url);
+        assert!(source.next().await.is_none());
+        assert!(source.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn reconnects_to_original_url() {
+        let (url, mut requests) = serve(vec![
+            respond(REDIRECT_RESPONSE),
+            respond(OK_RESPONSE),
+            respond(REDIRECT_RESPONSE),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source = event_source(&url);
+        assert!(is_open(&source.next().await));
+        assert_eq!(source.final_url().unwrap().path(), "/moved");
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).is_stream_ended());
+        assert!(is_open(&source.next().await));
+
+        for path in ["/", "/moved", "/", "/moved"] {
+            let request = requests.recv().await.unwrap();
+            assert!(request.starts_with(&format!("GET {path} ")), "{request}");
         }
     }
 
-    /// Gets a reference to the underlying response if this error was caused by an invalid [`Response`]
-    pub fn response(&self) -> Option<&Response> {
-        match &self.kind {
-            EventSourceErrorKind::InvalidStatusCode { response, .. }
-            | EventSourceErrorKind::InvalidContentType { response, .. } => Some(response),
+    /// Like [`event_source`] but the client hands redirects to the connector
+    fn connector_redirects(
+        request: impl FnOnce(reqwest::Client) -> RequestBuilder,
+    ) -> EventSource<Constant> {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        EventSource::new_with_retry(
+            request(client),
+            Constant::new(Duration::from_millis(10), Some(5)),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn temporary_redirects_start_over() {
+        let (url, mut requests) = serve(vec![
+            respond(REDIRECT_RESPONSE),
+            respond(OK_RESPONSE),
+            respond(REDIRECT_RESPONSE),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source = connector_redirects(|client| client.get(&url));
+        assert!(is_open(&source.next().await));
+        assert_eq!(source.final_url().unwrap().path(), "/moved");
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).is_stream_ended());
+        assert!(is_open(&source.next().await));
+        assert!(source.connector().permanent_url().is_none());
+
+        for path in ["/", "/moved", "/", "/moved"] {
+            let request = requests.recv().await.unwrap();
+            assert!(request.starts_with(&format!("GET {path} ")), "{request}");
+        }
+    }
 
-            _ => None,
+    #[tokio::test]
+    async fn reconnects_to_permanently_redirected_url() {
+        let (url, mut requests) = serve(vec![
+            respond(b"HTTP/1.1 308 Permanent Redirect\r\nlocation: /moved\r\nconnection: close\r\ncontent-length: 0\r\n\r\n"),
+            respond(OK_RESPONSE),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source = connector_redirects(|client| client.get(&url));
+        assert!(source.final_url().is_none());
+        assert!(is_open(&source.next().await));
+        assert_eq!(source.connector().permanent_url().unwrap().path(), "/moved");
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).is_stream_ended());
+        assert!(is_open(&source.next().await));
+
+        for path in ["/", "/moved", "/moved"] {
+            let request = requests.recv().await.unwrap();
+            assert!(request.starts_with(&format!("GET {path} ")), "{request}");
         }
     }
 
-    /// If this error comes from a [`Response`], return the raw response, collected status code and the [Content-Type][CONTENT_TYPE] header if it has been extracted already
-    pub fn into_response_err(self) -> Option<(Response, StatusCode, Option<HeaderValue>)> {
-        match self.kind {
-            EventSourceErrorKind::InvalidStatusCode { response, status } => {
-                Some((*response, status, None))
-            }
-            EventSourceErrorKind::InvalidContentType {
-                status,
-                content_type,
-                response,
-            } => Some((*response, status, Some(content_type))),
-            _ => None,
+    #[tokio::test]
+    async fn redirects_drop_credentials_across_origins() {
+        let (other_url, mut other_requests) =
+            serve(vec![respond(OK_RESPONSE), respond(OK_RESPONSE)]).await;
+        let redirect = format!(
+            "HTTP/1.1 308 Permanent Redirect\r\nlocation: {other_url}\r\nconnection: close\r\ncontent-length: 0\r\n\r\n"
+        );
+        let (url, mut requests) = serve(vec![respond(redirect.leak().as_bytes())]).await;
+
+        let mut source = connector_redirects(|client| {
+            client
+                .get(&url)
+                .bearer_auth("secret")
+                .header("cookie", "session=secret")
+        });
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).is_stream_ended());
+        assert!(is_open(&source.next().await));
+
+        let request = requests.recv().await.unwrap().to_lowercase();
+        assert!(
+            request.contains("authorization: bearer secret"),
+            "{request}"
+        );
+        // both the redirected connection and the reconnect
+        for _ in 0..2 {
+            let request = other_requests.recv().await.unwrap().to_lowercase();
+            assert!(!request.contains("authorization"), "{request}");
+            assert!(!request.contains("cookie"), "{request}");
         }
     }
 
-    /// Is this error because the stream has stopped?
-    pub fn is_stream_ended(&self) -> bool {
-        matches!(self.kind, EventSourceErrorKind::StreamEnded)
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("120")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(&HeaderValue::from_static("soon")), None);
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("18446744073709551615")),
+            Some(MAX_DELAY)
+        );
+
+        let in_an_hour =
+            httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(3600));
+        let parsed = parse_retry_after(&HeaderValue::from_str(&in_an_hour).unwrap()).unwrap();
+        assert!(parsed > Duration::from_secs(3590) && parsed <= Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn waits_for_retry_after() {
+        let (url, _requests) = serve(vec![
+            respond(
+                b"HTTP/1.1 429 Too Many Requests\r\nretry-after: 1\r\ncontent-length: 0\r\n\r\n",
+            ),
+            respond(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n"),
+        ])
+        .await;
+
+        let mut source = event_source(&url);
+        let rate_limited = expect_err(source.next().await);
+        assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(1)));
+
+        let start = std::time::Instant::now();
+        let unavailable = expect_err(source.next().await);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(unavailable.retry_after(), None);
+        // the server's delay is waited out but the backoff carries on from the policy's own delay
+        assert_eq!(
+            unavailable.retry_state().map(|(_, delay)| delay),
+            Some(Duration::from_millis(10))
+        );
     }
-}
 
-impl From<ReqwestError> for EventSourceErrorKind {
-    fn from(value: ReqwestError) -> Self {
-        Self::Transport(value)
+    #[tokio::test]
+    async fn default_closes_on_not_found() {
+        let (url, mut requests) = serve(vec![
+            respond(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n"),
+            respond(OK_RESPONSE),
+        ])
+        .await;
+
+        let mut source = EventSource::new(reqwest::Client::new().get(&url)).unwrap();
+        let not_found = expect_err(source.next().await);
+        assert_eq!(not_found.status_code(), Some(StatusCode::NOT_FOUND));
+        assert!(source.next().await.is_none());
+
+        requests.recv().await.unwrap();
+        assert!(requests.try_recv().is_err());
     }
-}
 
-impl From<EventStreamError<ReqwestError>> for EventSourceErrorKind {
-    fn from(value: EventStreamError<ReqwestError>) -> Self {
-        Self::Stream(value)
+    #[tokio::test]
+    async fn huge_retry_after_is_capped() {
+        let (url, _requests) = serve(vec![respond(
+            b"HTTP/1.1 503 Service Unavailable\r\nretry-after: 18446744073709551615\r\ncontent-length: 0\r\n\r\n",
+        )])
+        .await;
+
+        let mut source = EventSource::new(reqwest::Client::new().get(&url)).unwrap();
+        let unavailable = expect_err(source.next().await);
+        assert_eq!(unavailable.retry_after(), Some(MAX_DELAY));
+        // still waiting rather than panicking on the timer
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), source.next())
+                .await
+                .is_err()
+        );
     }
-}
 
-impl<R> futures_core::Stream for EventSource<R>
-where
-    R: RetryPolicy<EventSourceErrorKind>,
-{
-    type Item = Result<StreamEvent, EventSourceError>;
-
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut this = self.project();
-        loop {
-            match this.connection_state.as_mut().project() {
-                ConnectionStateProjection::Connecting {
-                    future,
-                    retry_state,
-                } => {
-                    let retry_state = *retry_state;
-                    match ready!(future.poll(cx)) {
-                        Ok(response) => {
-                            if let Err(kind) =
-                                this.handle_successful_response(response, retry_state)
-                            {
-                                return Poll::Ready(Some(Err(EventSourceError::new(
-                                    kind,
-                                    retry_state,
-                                ))));
-                            };
-                            return Poll::Ready(Some(Ok(StreamEvent::Open)));
-                        }
-                        Err(err) => {
-                            this.connection_state.set(ConnectionState::Closed);
-                            return Poll::Ready(Some(Err(EventSourceError::new(err, retry_state))));
-                        }
-                    }
-                }
-                ConnectionStateProjection::Retrying {
-                    delay,
-                    attempt_number,
-                    delay_duration,
-                } => {
-                    ready!(delay.poll(cx));
-                    let retry_state = Some((*attempt_number + 1, *delay_duration));
-                    if let Err(err) = this.initiate_connection(retry_state) {
-                        this.connection_state.set(ConnectionState::Closed);
-                        return Poll::Ready(Some(Err(EventSourceError::new(err, retry_state))));
-                    }
-                }
-                ConnectionStateProjection::Open {
-                    stream,
-                    retry_state,
-                } => {
-                    let retry_state = *retry_state;
-                    match ready!(stream.poll_next(cx)) {
-                        Some(Ok(event)) => {
-                            this.handle_event(&event);
-                            return Poll::Ready(Some(Ok(event.into())));
-                        }
-                        Some(Err(err)) => {
-                            let err_kind = err.into();
-                            this.handle_error(&err_kind, retry_state);
-                            return Poll::Ready(Some(Err(EventSourceError::new(
-                                err_kind,
-                                retry_state,
-                            ))));
-                        }
-                        None => {
-                            let err_kind = EventSourceErrorKind::StreamEnded;
-                            this.handle_error(&err_kind, retry_state);
-                            return Poll::Ready(Some(Err(EventSourceError::new(
-                                err_kind,
-                                retry_state,
-                            ))));
-                        }
-                    }
-                }
-                ConnectionStateProjection::Closed => return Poll::Ready(None),
-            }
+    #[tokio::test]
+    async fn retry_reset_after_event() {
+        let closes_after_event = || {
+            respond(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: hello\n\n")
+        };
+        let (url, _requests) = serve(vec![
+            closes_after_event(),
+            closes_after_event(),
+            closes_after_event(),
+        ])
+        .await;
+
+        let mut source = event_source(&url);
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).retry_state().is_none());
+        assert!(is_open(&source.next().await));
+        assert_eq!(event_data(source.next().await), "hello");
+        assert!(expect_err(source.next().await).retry_state().is_some());
+
+        let (url, _requests) = serve(vec![
+            closes_after_event(),
+            closes_after_event(),
+            closes_after_event(),
+        ])
+        .await;
+
+        let mut source = event_source(&url).with_retry_reset(RetryReset::OnEvent);
+        for _ in 0..3 {
+            assert!(is_open(&source.next().await));
+            assert_eq!(event_data(source.next().await), "hello");
+            assert!(expect_err(source.next().await).retry_state().is_none());
         }
     }
+
+    #[tokio::test]
+    async fn retry_reset_after_open_for() {
+        let (url, _requests) = serve(vec![
+            respond(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n"),
+            respond_then_hang(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n"),
+        ])
+        .await;
+
+        let mut source = event_source(&url)
+            .with_idle_timeout(Duration::from_millis(150))
+            .with_retry_reset(RetryReset::AfterOpenFor(Duration::from_millis(100)));
+        assert!(expect_err(source.next().await).is_status_code());
+        assert!(is_open(&source.next().await));
+        let err = expect_err(source.next().await);
+        assert!(err.is_idle_timeout());
+        assert!(err.retry_state().is_none());
+    }
 }
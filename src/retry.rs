@@ -2,7 +2,10 @@
 
 //! Helpers to handle connection delays when receiving errors
 
-use core::time::Duration;
+use core::{cell::Cell, time::Duration};
+
+pub mod combinators;
+pub use combinators::RetryPolicyExt;
 
 /// Describes how an [crate::EventSource] should retry
 pub trait RetryPolicy<E> {
@@ -10,6 +13,20 @@ pub trait RetryPolicy<E> {
     /// available. A policy may also return `None` if it does not want to retry
     fn retry(&self, err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration>;
 
+    /// Called instead of [`RetryPolicy::retry`] when the server has asked us to wait at least `retry_after`, such as
+    /// with a `Retry-After` header. Return the policy's own delay, the [crate::EventSource] waits for whichever is longer
+    /// but only this one is passed back as the last retry so the server's delay doesn't feed into the backoff. By
+    /// default this is the same as [`RetryPolicy::retry`].
+    fn retry_after(
+        &self,
+        err: &E,
+        last_retry: Option<(usize, Duration)>,
+        retry_after: Duration,
+    ) -> Option<Duration> {
+        let _ = retry_after;
+        self.retry(err, last_retry)
+    }
+
     /// Set a new reconnection time if received from an [`Event`][crate::event::Event]
     fn set_reconnection_time(&mut self, duration: Duration);
 }
@@ -113,6 +130,185 @@ impl<E> RetryPolicy<E> for Never {
     fn set_reconnection_time(&mut self, _duration: Duration) {}
 }
 
+/// Source of randomness for [JitteredBackoff], implemented for any `Fn() -> u64` so you can plug in your own RNG
+pub trait RandomSource {
+    /// Return a uniformly distributed random [u64]
+    fn next_u64(&self) -> u64;
+}
+
+impl<F> RandomSource for F
+where
+    F: Fn() -> u64,
+{
+    fn next_u64(&self) -> u64 {
+        self()
+    }
+}
+
+/// Small non-cryptographic [RandomSource], plenty for spreading out reconnects. Make sure each client gets a different seed.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: Cell<u64>,
+}
+
+impl SplitMix64 {
+    /// Create a new [SplitMix64] from a seed
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            state: Cell::new(seed),
+        }
+    }
+
+    /// Create a new [SplitMix64] seeded from the randomly keyed hasher std uses for [HashMap][std::collections::HashMap]
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> Self {
+        use std::hash::{BuildHasher, RandomState};
+        Self::new(RandomState::new().hash_one(0u8))
+    }
+}
+
+impl RandomSource for SplitMix64 {
+    fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9E3779B97F4A7C15);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// How [JitteredBackoff] randomises its delays, see <https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// A random delay between zero and the delay [ExponentialBackoff] would have used
+    Full,
+    /// A random delay between `start` and three times the last delay
+    Decorrelated,
+}
+
+/// A [RetryPolicy] which backs off exponentially with random jitter, so lots of clients dropped at once don't all reconnect at once
+#[derive(Debug, Clone)]
+pub struct JitteredBackoff<R = SplitMix64> {
+    /// The start of the backoff
+    pub start: Duration,
+    /// The factor of which to backoff by, only used by [Jitter::Full]
+    pub factor: f64,
+    /// The maximum duration to delay
+    pub max_duration: Option<Duration>,
+    /// The maximum number of retries before giving up
+    pub max_retries: Option<usize>,
+    /// How to apply the jitter
+    pub jitter: Jitter,
+    /// Where the randomness comes from
+    pub rng: R,
+}
+
+impl<R> JitteredBackoff<R> {
+    /// Create a new jittered exponential backoff retry policy
+    pub const fn new(
+        start: Duration,
+        factor: f64,
+        max_duration: Option<Duration>,
+        max_retries: Option<usize>,
+        jitter: Jitter,
+        rng: R,
+    ) -> Self {
+        Self {
+            start,
+            factor,
+            max_duration,
+            max_retries,
+            jitter,
+            rng,
+        }
+    }
+
+    /// Without a `max_duration` delays still stop at [MAX_DELAY] so they stay finite
+    fn cap(&self, duration: Duration) -> Duration {
+        duration.min(self.max_duration.unwrap_or(MAX_DELAY))
+    }
+}
+
+/// Picks a random [Duration] in `low..high`
+fn random_between(rng: &impl RandomSource, low: Duration, high: Duration) -> Duration {
+    // top 53 bits fit exactly in an f64 giving a uniform value in 0..1
+    let unit = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    low + high.saturating_sub(low).mul_f64(unit)
+}
+
+/// `base` to the power of `exp` by squaring since [f64::powi] needs std, overflow ends up as infinity
+fn powi(mut base: f64, mut exp: usize) -> f64 {
+    let mut result = 1.;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        exp >>= 1;
+    }
+    result
+}
+
+impl<E, R> RetryPolicy<E> for JitteredBackoff<R>
+where
+    R: RandomSource,
+{
+    fn retry(&self, _: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+        if let Some((retry_num, _)) = last_retry
+            && self
+                .max_retries
+                .is_some_and(|max_retries| retry_num >= max_retries)
+        {
+            return None;
+        }
+
+        let delay = match self.jitter {
+            Jitter::Full => {
+                // an EventSource numbers its first reconnect as retry 2, so that's the first step up from `start`
+                let exponent = last_retry.map_or(0, |(retry_num, _)| retry_num.saturating_sub(1));
+                let secs = self.start.as_secs_f64() * powi(self.factor, exponent);
+                // anything too big for a Duration, including infinity, saturates to the cap
+                let ceiling = self.cap(Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX));
+                random_between(&self.rng, Duration::ZERO, ceiling)
+            }
+            Jitter::Decorrelated => {
+                let last_delay = last_retry
+                    .map(|(_, last_delay)| last_delay)
+                    .unwrap_or(self.start);
+                let ceiling = self.cap(last_delay.saturating_mul(3).max(self.start));
+                self.cap(random_between(&self.rng, self.start, ceiling))
+            }
+        };
+
+        Some(delay)
+    }
+
+    fn set_reconnection_time(&mut self, duration: Duration) {
+        self.start = duration;
+        if let Some(max_duration) = self.max_duration {
+            self.max_duration = Some(max_duration.max(duration))
+        }
+    }
+}
+
+/// When an [crate::EventSource] forgets about earlier retries so that the next error starts backing off from scratch,
+/// otherwise a connection that was healthy for hours is treated as the next attempt of whatever outage came before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetryReset {
+    /// Keep counting retries for as long as the source lives
+    #[default]
+    Never,
+    /// Reset once a connection delivers an event
+    OnEvent,
+    /// Reset once a connection has stayed open for at least this long
+    AfterOpenFor(Duration),
+}
+
+/// Longest an [crate::EventSource] will wait before reconnecting, anything longer from a policy or a `Retry-After`
+/// header is cut down to this so the timer can't overflow
+pub const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// The default [RetryPolicy] when initializing an [crate::EventSource]
 pub const DEFAULT_RETRY: ExponentialBackoff = ExponentialBackoff::new(
     Duration::from_millis(300),
@@ -120,3 +316,86 @@ pub const DEFAULT_RETRY: ExponentialBackoff = ExponentialBackoff::new(
     Some(Duration::from_secs(5)),
     None,
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jittered(jitter: Jitter) -> JitteredBackoff {
+        JitteredBackoff::new(
+            Duration::from_millis(100),
+            2.,
+            Some(Duration::from_secs(5)),
+            Some(10),
+            jitter,
+            SplitMix64::new(42),
+        )
+    }
+
+    #[test]
+    fn full_jitter_bounds() {
+        let policy = jittered(Jitter::Full);
+        for _ in 0..100 {
+            let first = RetryPolicy::<()>::retry(&policy, &(), None).unwrap();
+            assert!(first < Duration::from_millis(100));
+
+            let second = RetryPolicy::<()>::retry(&policy, &(), Some((2, first))).unwrap();
+            assert!(second < Duration::from_millis(200));
+
+            let third = RetryPolicy::<()>::retry(&policy, &(), Some((3, second))).unwrap();
+            assert!(third < Duration::from_millis(400));
+
+            let capped = RetryPolicy::<()>::retry(&policy, &(), Some((9, first))).unwrap();
+            assert!(capped < Duration::from_secs(5));
+        }
+        assert_eq!(
+            RetryPolicy::<()>::retry(&policy, &(), Some((10, Duration::ZERO))),
+            None
+        );
+    }
+
+    #[test]
+    fn decorrelated_jitter_bounds() {
+        let policy = jittered(Jitter::Decorrelated);
+        let mut last = None;
+        for retry_num in 1..10 {
+            let delay = RetryPolicy::<()>::retry(&policy, &(), last).unwrap();
+            let upper = last
+                .map(|(_, last_delay): (usize, Duration)| last_delay * 3)
+                .unwrap_or(Duration::from_millis(300))
+                .min(Duration::from_secs(5));
+            assert!(delay >= Duration::from_millis(100).min(upper));
+            assert!(delay <= upper);
+            last = Some((retry_num, delay));
+        }
+    }
+
+    #[test]
+    fn jitter_without_max_duration_stays_finite() {
+        for jitter in [Jitter::Full, Jitter::Decorrelated] {
+            let policy = JitteredBackoff {
+                max_duration: None,
+                max_retries: None,
+                ..jittered(jitter)
+            };
+            for retry_num in [64, 1000, usize::MAX] {
+                let delay =
+                    RetryPolicy::<()>::retry(&policy, &(), Some((retry_num, Duration::MAX)))
+                        .unwrap();
+                assert!(delay <= MAX_DELAY);
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_spreads_clients() {
+        let delays = [0, 1, 2, 3, 4, 5, 6, 7].map(|seed| {
+            let policy = JitteredBackoff {
+                rng: SplitMix64::new(seed),
+                ..jittered(Jitter::Full)
+            };
+            RetryPolicy::<()>::retry(&policy, &(), Some((3, Duration::ZERO))).unwrap()
+        });
+        assert!(delays.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+}
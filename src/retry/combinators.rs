@@ -0,0 +1,287 @@
+//! Wrappers around a [`RetryPolicy`] for building up retry behaviour, created through [`RetryPolicyExt`]
+
+use core::time::Duration;
+
+use super::RetryPolicy;
+
+/// Combinators for any [`RetryPolicy`], implemented for every type so they can be chained straight off a policy
+///
+/// ```rust
+/// use core::time::Duration;
+/// use sseer::retry::{DEFAULT_RETRY, Never, RetryPolicy, RetryPolicyExt};
+///
+/// let policy = DEFAULT_RETRY
+///     .with_max_retries(5)
+///     .only_if(|err: &&str| *err != "unauthorized");
+///
+/// assert!(policy.retry(&"timed out", None).is_some());
+/// assert!(policy.retry(&"unauthorized", None).is_none());
+/// assert!(Never.or_else(policy).retry(&"timed out", Some((5, Duration::ZERO))).is_none());
+/// ```
+pub trait RetryPolicyExt: Sized {
+    /// Give up once `max_retries` retries have been attempted, on top of whatever the policy already does
+    fn with_max_retries(self, max_retries: usize) -> MaxRetries<Self> {
+        MaxRetries {
+            policy: self,
+            max_retries,
+        }
+    }
+
+    /// Only retry errors that `predicate` returns true for
+    fn only_if<F>(self, predicate: F) -> OnlyIf<Self, F> {
+        OnlyIf {
+            policy: self,
+            predicate,
+        }
+    }
+
+    /// Ask `other` whenever this policy gives up
+    fn or_else<P>(self, other: P) -> OrElse<Self, P> {
+        OrElse {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Give up once waiting for the next retry would take longer than `max_elapsed` since the first error of the current
+    /// run of errors
+    #[cfg(feature = "std")]
+    fn with_max_elapsed(self, max_elapsed: Duration) -> MaxElapsed<Self> {
+        MaxElapsed {
+            policy: self,
+            max_elapsed,
+            started: core::cell::Cell::new(None),
+        }
+    }
+}
+
+impl<P> RetryPolicyExt for P {}
+
+/// [`RetryPolicy`] returned by [`RetryPolicyExt::with_max_retries`]
+#[derive(Debug, Clone)]
+pub struct MaxRetries<P> {
+    policy: P,
+    max_retries: usize,
+}
+
+impl<E, P> RetryPolicy<E> for MaxRetries<P>
+where
+    P: RetryPolicy<E>,
+{
+    fn retry(&self, err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+        if self.is_exhausted(last_retry) {
+            return None;
+        }
+        self.policy.retry(err, last_retry)
+    }
+
+    fn retry_after(
+        &self,
+        err: &E,
+        last_retry: Option<(usize, Duration)>,
+        retry_after: Duration,
+    ) -> Option<Duration> {
+        if self.is_exhausted(last_retry) {
+            return None;
+        }
+        self.policy.retry_after(err, last_retry, retry_after)
+    }
+
+    fn set_reconnection_time(&mut self, duration: Duration) {
+        self.policy.set_reconnection_time(duration)
+    }
+}
+
+impl<P> MaxRetries<P> {
+    fn is_exhausted(&self, last_retry: Option<(usize, Duration)>) -> bool {
+        last_retry.is_some_and(|(retry_num, _)| retry_num >= self.max_retries)
+    }
+}
+
+/// [`RetryPolicy`] returned by [`RetryPolicyExt::only_if`]
+#[derive(Debug, Clone)]
+pub struct OnlyIf<P, F> {
+    policy: P,
+    predicate: F,
+}
+
+impl<E, P, F> RetryPolicy<E> for OnlyIf<P, F>
+where
+    P: RetryPolicy<E>,
+    F: Fn(&E) -> bool,
+{
+    fn retry(&self, err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+        if !(self.predicate)(err) {
+            return None;
+        }
+        self.policy.retry(err, last_retry)
+    }
+
+    fn retry_after(
+        &self,
+        err: &E,
+        last_retry: Option<(usize, Duration)>,
+        retry_after: Duration,
+    ) -> Option<Duration> {
+        if !(self.predicate)(err) {
+            return None;
+        }
+        self.policy.retry_after(err, last_retry, retry_after)
+    }
+
+    fn set_reconnection_time(&mut self, duration: Duration) {
+        self.policy.set_reconnection_time(duration)
+    }
+}
+
+/// [`RetryPolicy`] returned by [`RetryPolicyExt::or_else`]
+#[derive(Debug, Clone)]
+pub struct OrElse<P1, P2> {
+    first: P1,
+    second: P2,
+}
+
+impl<E, P1, P2> RetryPolicy<E> for OrElse<P1, P2>
+where
+    P1: RetryPolicy<E>,
+    P2: RetryPolicy<E>,
+{
+    fn retry(&self, err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+        self.first
+            .retry(err, last_retry)
+            .or_else(|| self.second.retry(err, last_retry))
+    }
+
+    fn retry_after(
+        &self,
+        err: &E,
+        last_retry: Option<(usize, Duration)>,
+        retry_after: Duration,
+    ) -> Option<Duration> {
+        self.first
+            .retry_after(err, last_retry, retry_after)
+            .or_else(|| self.second.retry_after(err, last_retry, retry_after))
+    }
+
+    fn set_reconnection_time(&mut self, duration: Duration) {
+        self.first.set_reconnection_time(duration);
+        self.second.set_reconnection_time(duration);
+    }
+}
+
+/// [`RetryPolicy`] returned by [`RetryPolicyExt::with_max_elapsed`]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct MaxElapsed<P> {
+    policy: P,
+    max_elapsed: Duration,
+    // reset whenever `last_retry` is None as that's the start of a new run of errors
+    started: core::cell::Cell<Option<std::time::Instant>>,
+}
+
+#[cfg(feature = "std")]
+impl<P> MaxElapsed<P> {
+    fn check(
+        &self,
+        last_retry: Option<(usize, Duration)>,
+        delay: Option<Duration>,
+    ) -> Option<Duration> {
+        let now = std::time::Instant::now();
+        let started = match (last_retry, self.started.get()) {
+            (Some(_), Some(started)) => started,
+            _ => {
+                self.started.set(Some(now));
+                now
+            }
+        };
+
+        let delay = delay?;
+        if now.saturating_duration_since(started) + delay > self.max_elapsed {
+            None
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E, P> RetryPolicy<E> for MaxElapsed<P>
+where
+    P: RetryPolicy<E>,
+{
+    fn retry(&self, err: &E, last_retry: Option<(usize, Duration)>) -> Option<Duration> {
+        self.check(last_retry, self.policy.retry(err, last_retry))
+    }
+
+    fn retry_after(
+        &self,
+        err: &E,
+        last_retry: Option<(usize, Duration)>,
+        retry_after: Duration,
+    ) -> Option<Duration> {
+        // the whole wait counts towards the elapsed time even though only the policy's delay is returned
+        let delay = self.policy.retry_after(err, last_retry, retry_after);
+        self.check(last_retry, delay.map(|delay| delay.max(retry_after)))?;
+        delay
+    }
+
+    fn set_reconnection_time(&mut self, duration: Duration) {
+        self.policy.set_reconnection_time(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::{Constant, Never};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn max_retries() {
+        let policy = Constant::new(SECOND, None).with_max_retries(2);
+        assert_eq!(policy.retry(&(), None), Some(SECOND));
+        assert_eq!(policy.retry(&(), Some((1, SECOND))), Some(SECOND));
+        assert_eq!(policy.retry(&(), Some((2, SECOND))), None);
+    }
+
+    #[test]
+    fn only_if() {
+        let policy = Constant::new(SECOND, None).only_if(|err: &u16| *err >= 500);
+        assert_eq!(policy.retry(&503, None), Some(SECOND));
+        assert_eq!(policy.retry(&401, None), None);
+        assert_eq!(policy.retry_after(&401, None, SECOND * 5), None);
+        assert_eq!(policy.retry_after(&503, None, SECOND * 5), Some(SECOND));
+    }
+
+    #[test]
+    fn or_else() {
+        let policy = Never.or_else(Constant::new(SECOND, None));
+        assert_eq!(policy.retry(&(), None), Some(SECOND));
+
+        let mut policy = Constant::new(SECOND, None)
+            .only_if(|err: &u16| *err >= 500)
+            .or_else(Constant::new(SECOND * 2, Some(1)));
+        assert_eq!(policy.retry(&503, None), Some(SECOND));
+        assert_eq!(policy.retry(&401, None), Some(SECOND * 2));
+        assert_eq!(policy.retry(&401, Some((1, SECOND))), None);
+
+        policy.set_reconnection_time(SECOND * 3);
+        assert_eq!(policy.retry(&401, None), Some(SECOND * 3));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn max_elapsed() {
+        let policy = Constant::new(SECOND, None).with_max_elapsed(SECOND * 3);
+        assert_eq!(policy.retry(&(), None), Some(SECOND));
+        assert_eq!(policy.retry(&(), Some((1, SECOND))), Some(SECOND));
+
+        let policy = Constant::new(SECOND * 2, None).with_max_elapsed(SECOND);
+        assert_eq!(policy.retry(&(), None), None);
+
+        let policy = Constant::new(SECOND, None).with_max_elapsed(SECOND * 3);
+        assert_eq!(policy.retry_after(&(), None, SECOND * 2), Some(SECOND));
+        assert_eq!(policy.retry_after(&(), None, SECOND * 5), None);
+    }
+}
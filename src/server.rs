@@ -0,0 +1,283 @@
+//! Serving SSE from any server built on the [`http`] crate types, [`sse_response`] turns a [`Stream`] of [`Event`]s
+//! into an [`http::Response`] that frameworks like axum can return as-is, and [`KeepAlive`] does the same encoding for
+//! anything else that takes a stream of bytes
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use bytes::{Bytes, BytesMut};
+use bytes_utils::Str;
+use futures_core::Stream;
+use futures_timer::Delay;
+use http::{
+    HeaderValue, Response,
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+};
+use http_body::{Body, Frame};
+use pin_project_lite::pin_project;
+
+use crate::{encoder::EventEncoder, errors::EncodeError, event::Event, http::EVENT_STREAM_MIME};
+
+/// Comment sent by default when the connection has been idle for the keep-alive interval
+const KEEP_ALIVE_COMMENT: Str = Str::from_static("keep-alive");
+
+pin_project! {
+    /// [`Stream`] that encodes each [`Event`] into its own [`Bytes`] and sends a `: keep-alive` comment whenever no event
+    /// has been sent for the interval, stopping proxies from dropping quiet connections
+    ///
+    /// Ready to hand to any server or transport that takes a stream of bytes, an event that can't be
+    /// [encoded][EventEncoder::encode] is yielded as an [`EncodeError`] and the stream carries on.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use futures::StreamExt;
+    /// use sseer::{event::Event, server::KeepAlive};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let events = futures::stream::pending::<Event>();
+    /// let mut bytes = KeepAlive::new(events, Duration::from_millis(10));
+    /// assert_eq!(bytes.next().await.unwrap().unwrap(), ": keep-alive\n");
+    /// # }
+    /// ```
+    #[derive(Debug)]
+    pub struct KeepAlive<S> {
+        #[pin]
+        stream: S,
+        encoder: EventEncoder,
+        #[pin]
+        delay: Option<Delay>,
+        interval: Duration,
+        comment: Str,
+        buffer: BytesMut,
+    }
+}
+
+impl<S> KeepAlive<S> {
+    /// Create a new [`KeepAlive`] that sends a comment after `interval` without any events
+    pub fn new(stream: S, interval: Duration) -> Self {
+        Self {
+            delay: Some(Delay::new(interval)),
+            interval,
+            ..Self::without_keep_alive(stream)
+        }
+    }
+
+    /// Only encodes, for an [`SseBody`] without keep-alive comments
+    fn without_keep_alive(stream: S) -> Self {
+        Self {
+            stream,
+            encoder: EventEncoder::new(),
+            delay: None,
+            interval: Duration::ZERO,
+            comment: KEEP_ALIVE_COMMENT,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Send `comment` instead of `keep-alive`, line breaks are split across multiple comment lines
+    pub fn with_comment(mut self, comment: impl Into<Str>) -> Self {
+        self.comment = comment.into();
+        self
+    }
+
+    /// Mutable reference to the [`EventEncoder`], to set the last event id the client resumed from
+    pub fn encoder_mut(&mut self) -> &mut EventEncoder {
+        &mut self.encoder
+    }
+}
+
+impl<S> Stream for KeepAlive<S>
+where
+    S: Stream<Item = Event>,
+{
+    type Item = Result<Bytes, EncodeError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(event)) => this.encoder.encode(&event, this.buffer)?,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => match this.delay.as_mut().as_pin_mut() {
+                Some(delay) => {
+                    ready!(delay.poll(cx));
+                    this.encoder.encode_comment(this.comment, this.buffer);
+                }
+                None => return Poll::Pending,
+            },
+        }
+
+        if let Some(delay) = this.delay.as_pin_mut() {
+            delay.get_mut().reset(*this.interval);
+        }
+        Poll::Ready(Some(Ok(this.buffer.split().freeze())))
+    }
+}
+
+pin_project! {
+    /// [`Body`] that writes every [`Event`] from a [`Stream`] as its own frame, so servers flush each event as soon as
+    /// it's produced instead of waiting for a buffer to fill
+    ///
+    /// An event that can't be [encoded][EventEncoder::encode] ends the body with an [`EncodeError`].
+    #[derive(Debug)]
+    pub struct SseBody<S> {
+        #[pin]
+        stream: KeepAlive<S>,
+    }
+}
+
+impl<S> SseBody<S> {
+    /// Create a new [`SseBody`] without keep-alive comments
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: KeepAlive::without_keep_alive(stream),
+        }
+    }
+
+    /// Send a `: keep-alive` comment whenever no event has been sent for `interval`, see [`KeepAlive`]
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.stream.delay = Some(Delay::new(interval));
+        self.stream.interval = interval;
+        self
+    }
+
+    /// Mutable reference to the [`EventEncoder`], to set the last event id the client resumed from
+    pub fn encoder_mut(&mut self) -> &mut EventEncoder {
+        self.stream.encoder_mut()
+    }
+
+    /// Wrap the body in a `200 OK` [`Response`] with the `Content-Type: text/event-stream` and
+    /// `Cache-Control: no-cache` headers
+    pub fn into_response(self) -> Response<Self> {
+        let mut response = Response::new(self);
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(EVENT_STREAM_MIME));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+}
+
+impl<S> From<KeepAlive<S>> for SseBody<S> {
+    fn from(stream: KeepAlive<S>) -> Self {
+        Self { stream }
+    }
+}
+
+impl<S> Body for SseBody<S>
+where
+    S: Stream<Item = Event>,
+{
+    type Data = Bytes;
+    type Error = EncodeError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let bytes = ready!(self.project().stream.poll_next(cx));
+        Poll::Ready(bytes.map(|bytes| bytes.map(Frame::data)))
+    }
+}
+
+/// Turn a [`Stream`] of [`Event`]s into a `200 OK` [`Response`] with the SSE headers, see [`SseBody`] for keep-alive
+/// comments
+///
+/// ```rust
+/// use bytes_utils::Str;
+/// use sseer::event::Event;
+///
+/// let events = futures::stream::iter([Event {
+///     event: Str::from_static("message"),
+///     data: Str::from_static("hello"),
+///     id: Str::new(),
+///     retry: None,
+/// }]);
+/// let response = sseer::server::sse_response(events);
+/// assert_eq!(response.headers()["content-type"], "text/event-stream");
+/// ```
+pub fn sse_response<S>(stream: S) -> Response<SseBody<S>>
+where
+    S: Stream<Item = Event>,
+{
+    SseBody::new(stream).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{StreamExt, stream};
+    use http_body_util::BodyExt;
+
+    fn event(data: &'static str) -> Event {
+        Event {
+            event: Str::from_static("message"),
+            data: Str::from_static(data),
+            id: Str::new(),
+            retry: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn body_frame_per_event() {
+        let mut body = sse_response(stream::iter([event("one"), event("two\nthree")])).into_body();
+        assert_eq!(
+            body.frame().await.unwrap().unwrap().into_data().unwrap(),
+            "data: one\n\n"
+        );
+        assert_eq!(
+            body.frame().await.unwrap().unwrap().into_data().unwrap(),
+            "data: two\ndata: three\n\n"
+        );
+        assert!(body.frame().await.is_none());
+
+        let mut bad = event("bad");
+        bad.id = Str::from_static("a\nb");
+        let mut body = SseBody::new(stream::iter([bad]));
+        assert!(matches!(
+            body.frame().await,
+            Some(Err(EncodeError::IdContainsNewline))
+        ));
+    }
+
+    #[tokio::test]
+    async fn body_keep_alive() {
+        let events = stream::iter([event("one")]).chain(stream::pending());
+        let mut body = SseBody::new(events).with_keep_alive(Duration::from_millis(20));
+
+        assert_eq!(
+            body.frame().await.unwrap().unwrap().into_data().unwrap(),
+            "data: one\n\n"
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                body.frame().await.unwrap().unwrap().into_data().unwrap(),
+                ": keep-alive\n"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn keep_alive_between_events() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut bytes = KeepAlive::new(rx, Duration::from_millis(50)).with_comment("ping");
+
+        tx.unbounded_send(event("one")).unwrap();
+        assert_eq!(bytes.next().await.unwrap().unwrap(), "data: one\n\n");
+        assert_eq!(bytes.next().await.unwrap().unwrap(), ": ping\n");
+
+        // sending an event pushes the next comment back
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        tx.unbounded_send(event("two")).unwrap();
+        let start = std::time::Instant::now();
+        assert_eq!(bytes.next().await.unwrap().unwrap(), "data: two\n\n");
+        assert_eq!(bytes.next().await.unwrap().unwrap(), ": ping\n");
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        drop(tx);
+        assert!(bytes.next().await.is_none());
+    }
+}